@@ -7,11 +7,11 @@ pub mod collection {
 
     use crate::init;
 
-    pub fn parse_album(filepath: &String) -> Result<Album, serde_json::Error> {
-        let file = File::open(filepath).expect("Failed to open file");
+    pub fn parse_album(filepath: &String) -> Result<Album, crate::Error> {
+        let file = File::open(filepath)?;
         let reader = BufReader::new(file);
 
-        serde_json::from_reader(reader)
+        Ok(serde_json::from_reader(reader)?)
     }
 
     #[derive(Clone, Debug, Default, Deserialize, Serialize)]
@@ -40,4 +40,20 @@ pub mod collection {
         // In seconds
         pub duration: f64,
     }
+
+    impl Track {
+        /// Creates a Track from a song, computing the duration from the song's audio headers
+        pub fn from_song(song: &crate::song::Song) -> Result<Track, crate::Error> {
+            match song.calculate_duration() {
+                Ok(duration) => Ok(Track {
+                    title: song.title.clone(),
+                    artist: song.artist.clone(),
+                    disc: song.disc,
+                    track: song.track,
+                    duration,
+                }),
+                Err(err) => Err(err),
+            }
+        }
+    }
 }
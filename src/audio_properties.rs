@@ -0,0 +1,71 @@
+use std::default::Default;
+
+use crate::init;
+
+use serde::{Deserialize, Serialize};
+
+/// Sample rates above this are considered hi-res
+const HI_RES_SAMPLE_RATE: i32 = 48000;
+/// Bit depths above this are considered hi-res
+const HI_RES_BIT_DEPTH: i32 = 16;
+
+/// Names of the codecs reported in AudioProperties
+pub mod codec {
+    pub const FLAC: &str = "flac";
+    pub const MP1: &str = "mp1";
+    pub const MP2: &str = "mp2";
+    pub const MP3: &str = "mp3";
+    pub const PCM: &str = "pcm";
+    pub const PCM_FLOAT: &str = "pcm_float";
+    pub const WAV: &str = "wav";
+}
+
+/// Technical properties of a song's audio stream
+#[derive(Clone, Debug, Default, Deserialize, PartialEq, Serialize, utoipa::ToSchema)]
+pub struct AudioProperties {
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub codec: String,
+    /// In Hz
+    #[serde(skip_serializing_if = "init::is_zero")]
+    pub sample_rate: i32,
+    /// Bits per sample. 0 for lossy codecs
+    #[serde(skip_serializing_if = "init::is_zero")]
+    pub bit_depth: i32,
+    #[serde(skip_serializing_if = "init::is_zero")]
+    pub channels: i32,
+    /// Average bitrate in kbps
+    #[serde(skip_serializing_if = "init::is_zero")]
+    pub bitrate: i32,
+}
+
+impl AudioProperties {
+    /// Whether the audio exceeds CD quality
+    pub fn is_hi_res(&self) -> bool {
+        self.sample_rate > HI_RES_SAMPLE_RATE || self.bit_depth > HI_RES_BIT_DEPTH
+    }
+}
+
+/// Gets the audio properties of song data from its audio headers
+pub fn from_data(data: &[u8]) -> Result<AudioProperties, crate::Error> {
+    crate::metadata::audio_properties(&mut std::io::Cursor::new(data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_hi_res() {
+        let mut properties = AudioProperties {
+            codec: String::from(codec::FLAC),
+            sample_rate: 44100,
+            bit_depth: 16,
+            channels: 2,
+            bitrate: 900,
+        };
+        assert!(!properties.is_hi_res());
+
+        properties.bit_depth = 24;
+        assert!(properties.is_hi_res());
+    }
+}
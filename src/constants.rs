@@ -10,11 +10,72 @@ pub mod file_extensions {
         pub const JPGEXTENSION: &str = ".jpg";
         pub const JPEGEXTENSION: &str = ".jpeg";
         pub const PNGEXTENSION: &str = ".png";
+        pub const WEBPEXTENSION: &str = ".webp";
+        pub const GIFEXTENSION: &str = ".gif";
     }
 }
 
+pub mod magic {
+    pub const FLAC: &[u8] = b"fLaC";
+    pub const ID3: &[u8] = b"ID3";
+    pub const RIFF: &[u8] = b"RIFF";
+    pub const WAVE: &[u8] = b"WAVE";
+    pub const PNG: &[u8] = b"\x89PNG\r\n\x1a\n";
+    pub const JPEG: &[u8] = &[0xFF, 0xD8, 0xFF];
+    /// Follows a RIFF header in WebP files
+    pub const WEBP: &[u8] = b"WEBP";
+    pub const GIF87A: &[u8] = b"GIF87a";
+    pub const GIF89A: &[u8] = b"GIF89a";
+}
+
+pub mod mime {
+    pub const PNG: &str = "image/png";
+    pub const JPEG: &str = "image/jpeg";
+    pub const WEBP: &str = "image/webp";
+    pub const GIF: &str = "image/gif";
+}
+
 pub mod error {
+    pub const ID_NOT_INITIALIZED: &str = "Id has not been initialized";
     pub const DIRECTORY_NOT_INITIALIZED: &str = "Directory has not been initialized";
     pub const FILENAME_NOT_INITIALIZED: &str = "Filename has not bee initialized";
     pub const LAST_CHARACTER_IN_DIRECTORY: &str = "Could not access last character of directory";
+    pub const INVALID_FILENAME: &str =
+        "Filename must not be absolute, contain separators or refer to a parent directory";
+    pub const NOT_A_FLAC_FILE: &str = "File is not a FLAC file";
+    pub const INVALID_FLAC_METADATA: &str = "FLAC metadata is invalid";
+    pub const METADATA_BLOCK_TOO_LARGE: &str = "FLAC metadata block must be smaller than 16 MiB";
+    pub const INVALID_ID3_TAG: &str = "ID3 tag is invalid";
+    pub const UNSUPPORTED_ID3_VERSION: &str = "Only ID3v2.3 and ID3v2.4 tags are supported";
+    pub const INVALID_WAV_FILE: &str = "File is not a valid WAV file";
+    pub const INVALID_MPEG_STREAM: &str = "No MPEG audio frames were found";
+    pub const UNSUPPORTED_AUDIO_FORMAT: &str = "Unsupported audio format";
+    pub const DATA_DOES_NOT_MATCH_EXTENSION: &str = "Data does not match the file extension";
+    pub const UNSUPPORTED_MUSIC_TYPE: &str = "Unsupported MusicTypes";
+    pub const FILENAME_COLLISION: &str = "Could not generate a filename that is not taken";
+    pub const INVALID_TEMPLATE: &str = "Filename template is invalid";
+    pub const FILE_EMPTY: &str = "File is empty";
+    pub const FILE_DOES_NOT_EXIST: &str = "Cannot delete file that does not exist";
+    pub const FILE_ALREADY_EXISTS: &str = "File already exists";
+    pub const RANGE_NOT_SATISFIABLE: &str = "Range is not satisfiable";
+    pub const CHECKSUM_NOT_SET: &str = "Checksum has not been set";
+    pub const INVALID_CHECKSUM: &str = "Checksum is not a hex encoded SHA-256 digest";
+    pub const NO_EMBEDDED_COVERART: &str = "Song does not have embedded cover art";
+    pub const COVERART_DATA_EMPTY: &str = "CoverArt data is empty";
+    pub const UNSUPPORTED_COVERART_FILE_TYPE: &str = "Unsupported CoverArt file type";
+    pub const UNSUPPORTED_KEY_FORMAT: &str = "Key format is not supported by the algorithm";
+    pub const DUPLICATE_KEY_ID: &str = "Key id is already used by another key";
+    pub const UNKNOWN_KEY_ID: &str = "Token was not signed by a known key";
+    pub const NO_SIGNING_KEY: &str = "No key is signing tokens";
+    pub const REFRESH_TOKEN_REUSED: &str =
+        "Refresh token was already used, every token of its family has been revoked";
+    pub const TOKEN_REVOKED: &str = "Token has been revoked";
+    pub const INVALID_TOKEN: &str = "Token is malformed or its signature is invalid";
+    pub const TOKEN_EXPIRED: &str = "Token has expired";
+    pub const TOKEN_NOT_YET_VALID: &str = "Token is not valid yet";
+    pub const INVALID_TOKEN_ISSUER: &str = "Token was not issued by the expected issuer";
+    pub const INVALID_TOKEN_AUDIENCE: &str = "Token is not meant for the expected audience";
+    pub const INVALID_IMAGE: &str = "Image headers are invalid";
+    pub const IMAGE_TOO_LARGE: &str = "Image exceeds the allowed dimensions or size";
+    pub const IMAGE_NOT_SQUARE: &str = "Image is not square enough";
 }
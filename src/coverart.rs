@@ -1,9 +1,3 @@
-use std::io::Write;
-
-use rand::Rng;
-
-const FILENAME_LENGTH: i32 = 16;
-
 #[derive(Clone, Debug, Default, serde::Deserialize, serde::Serialize, utoipa::ToSchema)]
 pub struct CoverArt {
     pub id: uuid::Uuid,
@@ -15,6 +9,50 @@ pub struct CoverArt {
     #[serde(skip)]
     pub data: Vec<u8>,
     pub song_id: uuid::Uuid,
+    /// Hex encoded SHA-256 checksum of the cover art's file
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub checksum: String,
+    /// In pixels
+    #[serde(default, skip_serializing_if = "crate::init::is_zero")]
+    pub width: i32,
+    /// In pixels
+    #[serde(default, skip_serializing_if = "crate::init::is_zero")]
+    pub height: i32,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub mime_type: String,
+    /// In bytes
+    #[serde(default, skip_serializing_if = "crate::init::is_size_not_set")]
+    pub size: i64,
+    /// Id of the coverart a thumbnail was made from. Nil for original coverart
+    #[serde(default, skip_serializing_if = "crate::init::is_uuid_nil")]
+    pub original_id: uuid::Uuid,
+}
+
+/// Largest width and height of cover art allowed by default, in pixels
+pub const DEFAULT_MAX_DIMENSION: i32 = 3000;
+/// Largest cover art file allowed by default, in bytes
+pub const DEFAULT_MAX_SIZE: i64 = 10 * 1024 * 1024;
+
+/// Limits enforced on uploaded cover art
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImageLimits {
+    pub max_width: i32,
+    pub max_height: i32,
+    pub max_size: i64,
+    /// Largest ratio of the longer side to the shorter side. 1.0 requires a square image, and
+    /// None allows any shape
+    pub max_aspect_ratio: Option<f64>,
+}
+
+impl Default for ImageLimits {
+    fn default() -> Self {
+        ImageLimits {
+            max_width: DEFAULT_MAX_DIMENSION,
+            max_height: DEFAULT_MAX_DIMENSION,
+            max_size: DEFAULT_MAX_SIZE,
+            max_aspect_ratio: None,
+        }
+    }
 }
 
 pub mod init {
@@ -30,56 +68,208 @@ pub mod init {
 
 impl CoverArt {
     /// Saves the coverart to the filesystem
-    pub fn save_to_filesystem(&self) -> Result<(), std::io::Error> {
-        match self.get_path() {
-            Ok(path) => match std::fs::File::create(&path) {
-                Ok(mut file) => match file.write_all(&self.data) {
-                    Ok(_) => Ok(()),
-                    Err(err) => Err(err),
-                },
-                Err(err) => Err(err),
-            },
+    pub fn save_to_filesystem(&self) -> Result<(), crate::Error> {
+        self.save_to_filesystem_with_mode(crate::types::WriteMode::Overwrite)
+    }
+
+    /// Saves the coverart to the filesystem atomically. Depending on the mode, an existing file is
+    /// either replaced or left untouched with an error returned
+    pub fn save_to_filesystem_with_mode(
+        &self,
+        mode: crate::types::WriteMode,
+    ) -> Result<(), crate::Error> {
+        self.save_to_storage(&crate::storage::LocalStorage, mode)
+    }
+
+    /// Saves the coverart's data to a storage backend under the coverart's path
+    pub fn save_to_storage<S: crate::storage::Storage>(
+        &self,
+        storage: &S,
+        mode: crate::types::WriteMode,
+    ) -> Result<(), crate::Error> {
+        match self.path() {
+            Ok(path) => storage.put(&path, &self.data, mode),
             Err(err) => Err(err),
         }
     }
 
     /// Removes the coverart from the filesystem
-    pub fn remove_from_filesystem(&self) -> Result<(), std::io::Error> {
-        match self.get_path() {
-            Ok(path) => {
-                let p = std::path::Path::new(&path);
-                if p.exists() {
-                    match std::fs::remove_file(p) {
-                        Ok(_) => Ok(()),
-                        Err(err) => Err(err),
-                    }
-                } else {
-                    Err(std::io::Error::other(
-                        "Cannot delete file that does not exist",
-                    ))
-                }
+    pub fn remove_from_filesystem(&self) -> Result<(), crate::Error> {
+        self.remove_from_storage(&crate::storage::LocalStorage)
+    }
+
+    /// Removes the coverart from a storage backend
+    pub fn remove_from_storage<S: crate::storage::Storage>(
+        &self,
+        storage: &S,
+    ) -> Result<(), crate::Error> {
+        match self.path() {
+            Ok(path) => storage.delete(&path),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Verifies that the coverart's data matches the extension of its filename
+    pub fn validate_data(&self) -> Result<crate::types::CoverArtType, crate::Error> {
+        let extension = match std::path::Path::new(&self.filename).extension() {
+            Some(extension) => extension.to_string_lossy(),
+            None => return Err(crate::Error::DataDoesNotMatchExtension),
+        };
+
+        let detected = detect_coverart_type(&self.data);
+        let expected = match crate::types::CoverArtType::from_extension(&extension) {
+            crate::types::CoverArtType::JpgExtension => crate::types::CoverArtType::JpegExtension,
+            expected => expected,
+        };
+
+        if detected != crate::types::CoverArtType::None && detected == expected {
+            Ok(detected)
+        } else {
+            Err(crate::Error::DataDoesNotMatchExtension)
+        }
+    }
+
+    /// Saves the coverart to the filesystem after verifying that the coverart's data matches
+    /// the extension of its filename
+    pub fn save_to_filesystem_validated(&self) -> Result<(), crate::Error> {
+        match self.validate_data() {
+            Ok(_) => self.save_to_filesystem(),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Sets the coverart's width, height, MIME type and size from its image headers. The
+    /// coverart's data is used when loaded, otherwise the file at the coverart's path is read
+    pub fn inspect_image(&mut self) -> Result<(), crate::Error> {
+        let (info, size) = self.read_image_info()?;
+        self.set_image_info(info, size)
+    }
+
+    /// Verifies that the coverart is within limits. The image is always inspected, so the
+    /// recorded dimensions and size are not trusted, and they are updated once the image passes
+    pub fn validate_limits(&mut self, limits: &ImageLimits) -> Result<(), crate::Error> {
+        let (info, size) = self.read_image_info()?;
+
+        let limit = |value: i64| u64::try_from(value).unwrap_or(0);
+        if u64::from(info.width) > limit(i64::from(limits.max_width))
+            || u64::from(info.height) > limit(i64::from(limits.max_height))
+            || size > limit(limits.max_size)
+        {
+            return Err(crate::Error::ImageTooLarge);
+        }
+
+        if let Some(max_aspect_ratio) = limits.max_aspect_ratio {
+            let longer = f64::from(info.width.max(info.height));
+            let shorter = f64::from(info.width.min(info.height).max(1));
+            if longer / shorter > max_aspect_ratio {
+                return Err(crate::Error::ImageNotSquare);
+            }
+        }
+
+        self.set_image_info(info, size)
+    }
+
+    /// Reads the image headers and size of the coverart
+    fn read_image_info(&self) -> Result<(crate::metadata::image::ImageInfo, u64), crate::Error> {
+        if self.data.is_empty() {
+            let data = io::to_data(self)?;
+            Ok((crate::metadata::image::inspect(&data)?, data.len() as u64))
+        } else {
+            Ok((
+                crate::metadata::image::inspect(&self.data)?,
+                self.data.len() as u64,
+            ))
+        }
+    }
+
+    /// Records the dimensions, MIME type and size of the coverart. Dimensions beyond what the
+    /// fields can hold are rejected rather than wrapped
+    fn set_image_info(
+        &mut self,
+        info: crate::metadata::image::ImageInfo,
+        size: u64,
+    ) -> Result<(), crate::Error> {
+        match (
+            i32::try_from(info.width),
+            i32::try_from(info.height),
+            i64::try_from(size),
+        ) {
+            (Ok(width), Ok(height), Ok(size)) => {
+                self.width = width;
+                self.height = height;
+                self.mime_type = info.mime_type;
+                self.size = size;
+                Ok(())
             }
+            _ => Err(crate::Error::ImageTooLarge),
+        }
+    }
+
+    /// Computes the SHA-256 checksum of the coverart. The coverart's data is used when loaded,
+    /// otherwise the file at the coverart's path is read
+    pub fn compute_checksum(&self) -> Result<String, crate::Error> {
+        self.compute_checksum_from_storage(&crate::storage::LocalStorage)
+    }
+
+    /// Computes the SHA-256 checksum of the coverart. The coverart's data is used when loaded,
+    /// otherwise the coverart is streamed from a storage backend
+    pub fn compute_checksum_from_storage<S: crate::storage::Storage>(
+        &self,
+        storage: &S,
+    ) -> Result<String, crate::Error> {
+        if !self.data.is_empty() {
+            return Ok(crate::util::checksum::sha256(&self.data));
+        }
+
+        match self.path() {
+            Ok(path) => Ok(crate::util::checksum::sha256_from_reader(
+                &mut storage.open(&path)?,
+            )?),
             Err(err) => Err(err),
         }
     }
 
-    /// Gets the path of the CoverArt
-    pub fn get_path(&self) -> Result<String, std::io::Error> {
-        if self.directory.is_empty() {
-            return Err(std::io::Error::other(
-                crate::constants::error::DIRECTORY_NOT_INITIALIZED,
-            ));
-        } else if self.filename.is_empty() {
-            return Err(std::io::Error::other(
-                crate::constants::error::FILENAME_NOT_INITIALIZED,
-            ));
+    /// Sets the coverart's checksum
+    pub fn update_checksum(&mut self) -> Result<(), crate::Error> {
+        self.checksum = self.compute_checksum()?;
+        Ok(())
+    }
+
+    /// Verifies the file at the coverart's path against the coverart's checksum. Returns false
+    /// when the file has changed
+    pub fn verify_file(&self) -> Result<bool, crate::Error> {
+        self.verify_file_in_storage(&crate::storage::LocalStorage)
+    }
+
+    /// Verifies the coverart stored in a storage backend against the coverart's checksum.
+    /// Returns false when the stored coverart has changed
+    pub fn verify_file_in_storage<S: crate::storage::Storage>(
+        &self,
+        storage: &S,
+    ) -> Result<bool, crate::Error> {
+        if self.checksum.is_empty() {
+            return Err(crate::Error::ChecksumNotSet);
+        }
+
+        match self.path() {
+            Ok(path) => Ok(
+                crate::util::checksum::sha256_from_reader(&mut storage.open(&path)?)?
+                    == self.checksum,
+            ),
+            Err(err) => Err(err),
         }
+    }
 
-        let directory = &self.directory;
-        let last_index = directory.len() - 1;
+    /// Gets the path of the CoverArt. Filenames that would escape the coverart's directory are
+    /// rejected
+    pub fn path(&self) -> Result<std::path::PathBuf, crate::Error> {
+        crate::util::path::join(&self.directory, &self.filename)
+    }
 
-        match crate::util::concatenate_path(directory, &self.filename, last_index) {
-            Ok(path) => Ok(path),
+    /// Gets the path of the CoverArt as a string
+    pub fn get_path(&self) -> Result<String, crate::Error> {
+        match self.path() {
+            Ok(path) => Ok(path.to_string_lossy().into_owned()),
             Err(err) => Err(err),
         }
     }
@@ -89,7 +279,7 @@ impl CoverArt {
 pub fn generate_filename(
     typ: crate::types::CoverArtType,
     randomize: bool,
-) -> Result<String, std::io::Error> {
+) -> Result<String, crate::Error> {
     let file_extension = match typ {
         crate::types::CoverArtType::PngExtension => {
             String::from(crate::constants::file_extensions::image::PNGEXTENSION)
@@ -100,26 +290,17 @@ pub fn generate_filename(
         crate::types::CoverArtType::JpgExtension => {
             String::from(crate::constants::file_extensions::image::JPGEXTENSION)
         }
-        crate::types::CoverArtType::None => {
-            return Err(std::io::Error::other("Unsupported CoverArtTypes"));
+        crate::types::CoverArtType::WebpExtension => {
+            String::from(crate::constants::file_extensions::image::WEBPEXTENSION)
         }
+        crate::types::CoverArtType::GifExtension => {
+            String::from(crate::constants::file_extensions::image::GIFEXTENSION)
+        }
+        crate::types::CoverArtType::None => return Err(crate::Error::UnsupportedCoverArtType),
     };
 
     let filename: String = if randomize {
-        let mut filename: String = String::from("coverart-");
-        let some_chars: String = String::from("abcdefghij0123456789");
-        let some_chars_length = some_chars.len();
-        let mut rng = rand::rng();
-
-        for _ in 0..FILENAME_LENGTH {
-            let index = rng.random_range(0..=some_chars_length);
-            let rando_char = some_chars.chars().nth(index);
-
-            if let Some(c) = rando_char {
-                filename.push(c);
-            }
-        }
-        format!("{filename}{file_extension}")
+        crate::util::filename::Generator::new("coverart-").generate(&file_extension)?
     } else {
         format!("coverart-output{file_extension}")
     };
@@ -127,23 +308,306 @@ pub fn generate_filename(
     Ok(filename)
 }
 
-pub mod io {
-    use std::io::Read;
+/// Detects the CoverArtType of image data from its leading bytes. JPEG data is reported as
+/// JpegExtension
+pub fn detect_coverart_type(data: &[u8]) -> crate::types::CoverArtType {
+    if data.starts_with(crate::constants::magic::PNG) {
+        crate::types::CoverArtType::PngExtension
+    } else if data.starts_with(crate::constants::magic::JPEG) {
+        crate::types::CoverArtType::JpegExtension
+    } else if data.starts_with(crate::constants::magic::RIFF)
+        && data.get(8..12) == Some(crate::constants::magic::WEBP)
+    {
+        crate::types::CoverArtType::WebpExtension
+    } else if data.starts_with(crate::constants::magic::GIF87A)
+        || data.starts_with(crate::constants::magic::GIF89A)
+    {
+        crate::types::CoverArtType::GifExtension
+    } else {
+        crate::types::CoverArtType::None
+    }
+}
+
+/// Gets the MIME type of a CoverArt file type such as "png" or "jpg"
+pub fn file_type_to_mime(file_type: &str) -> Option<&'static str> {
+    let file_type = file_type.trim_start_matches('.');
+    if file_type.eq_ignore_ascii_case("png") {
+        Some(crate::constants::mime::PNG)
+    } else if file_type.eq_ignore_ascii_case("jpeg") || file_type.eq_ignore_ascii_case("jpg") {
+        Some(crate::constants::mime::JPEG)
+    } else if file_type.eq_ignore_ascii_case("webp") {
+        Some(crate::constants::mime::WEBP)
+    } else if file_type.eq_ignore_ascii_case("gif") {
+        Some(crate::constants::mime::GIF)
+    } else {
+        None
+    }
+}
 
+/// Gets the CoverArt file type of a MIME type such as "image/png"
+pub fn mime_to_file_type(mime_type: &str) -> String {
+    match mime_type.split_once('/') {
+        Some((_, subtype)) => subtype.to_ascii_lowercase(),
+        None => mime_type.to_ascii_lowercase(),
+    }
+}
+
+pub mod io {
     /// Gets the raw data of the cover art
-    pub fn to_data(coverart: &super::CoverArt) -> Result<Vec<u8>, std::io::Error> {
-        match coverart.get_path() {
+    pub fn to_data(coverart: &super::CoverArt) -> Result<Vec<u8>, crate::Error> {
+        to_data_from_storage(&crate::storage::LocalStorage, coverart)
+    }
+
+    /// Gets the raw data of the cover art from a storage backend
+    pub fn to_data_from_storage<S: crate::storage::Storage>(
+        storage: &S,
+        coverart: &super::CoverArt,
+    ) -> Result<Vec<u8>, crate::Error> {
+        match coverart.path() {
+            Ok(path) => storage.get(&path),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Extracts the cover art embedded in a song's file. The front cover is preferred
+    pub fn from_song(song: &crate::song::Song) -> Result<super::CoverArt, crate::Error> {
+        from_song_in_storage(&crate::storage::LocalStorage, song)
+    }
+
+    /// Extracts the cover art embedded in a song stored in a storage backend. The front cover is
+    /// preferred
+    pub fn from_song_in_storage<S: crate::storage::Storage>(
+        storage: &S,
+        song: &crate::song::Song,
+    ) -> Result<super::CoverArt, crate::Error> {
+        match song.path() {
             Ok(path) => {
-                let mut file = std::fs::File::open(path)?;
-                let mut buffer = Vec::new();
-                match file.read_to_end(&mut buffer) {
-                    Ok(_) => Ok(buffer),
-                    Err(err) => Err(err),
+                let mut reader = std::io::BufReader::new(storage.open(&path)?);
+                let blocks = crate::metadata::flac::read_blocks(&mut reader)?;
+
+                match crate::metadata::flac::cover_picture(&blocks)? {
+                    Some(picture) => {
+                        let mut coverart = super::CoverArt {
+                            id: uuid::Uuid::nil(),
+                            title: picture.description,
+                            file_type: super::mime_to_file_type(&picture.mime_type),
+                            checksum: crate::util::checksum::sha256(&picture.data),
+                            mime_type: picture.mime_type,
+                            size: picture.data.len() as i64,
+                            data: picture.data,
+                            song_id: song.id,
+                            ..Default::default()
+                        };
+                        // The dimensions recorded in the block are often left unset, so they
+                        // are only used when the image cannot be read
+                        let fallback =
+                            (i32::try_from(picture.width), i32::try_from(picture.height));
+                        match (coverart.inspect_image(), fallback) {
+                            (Ok(_), _) => Ok(coverart),
+                            (Err(crate::Error::ImageTooLarge), _) => {
+                                Err(crate::Error::ImageTooLarge)
+                            }
+                            (Err(_), (Ok(width), Ok(height))) => {
+                                coverart.width = width;
+                                coverart.height = height;
+                                Ok(coverart)
+                            }
+                            (Err(_), _) => Err(crate::Error::ImageTooLarge),
+                        }
+                    }
+                    None => Err(crate::Error::NoEmbeddedCoverArt),
                 }
             }
             Err(err) => Err(err),
         }
     }
+
+    /// Embeds the cover art's data into a song's file as the front cover
+    pub fn embed_into_song(
+        coverart: &super::CoverArt,
+        song: &crate::song::Song,
+    ) -> Result<(), crate::Error> {
+        embed_into_song_in_storage(&crate::storage::LocalStorage, coverart, song)
+    }
+
+    /// Embeds the cover art's data into a song stored in a storage backend as the front cover
+    pub fn embed_into_song_in_storage<S: crate::storage::Storage>(
+        storage: &S,
+        coverart: &super::CoverArt,
+        song: &crate::song::Song,
+    ) -> Result<(), crate::Error> {
+        if coverart.data.is_empty() {
+            return Err(crate::Error::CoverArtDataEmpty);
+        }
+
+        let mime_type = match super::file_type_to_mime(&coverart.file_type) {
+            Some(mime_type) => mime_type,
+            None => return Err(crate::Error::UnsupportedCoverArtType),
+        };
+
+        let info = crate::metadata::image::inspect(&coverart.data).unwrap_or_default();
+        let picture = crate::metadata::flac::Picture {
+            picture_type: crate::metadata::flac::picture_type::FRONT_COVER,
+            mime_type: String::from(mime_type),
+            description: coverart.title.clone(),
+            width: info.width,
+            height: info.height,
+            data: coverart.data.clone(),
+            ..Default::default()
+        };
+
+        match song.path() {
+            Ok(path) => crate::metadata::flac::write_picture(storage, &path, &picture),
+            Err(err) => Err(err),
+        }
+    }
+}
+
+/// Resized variants of cover art for clients that do not need the full image
+pub mod thumbnail {
+    use std::path::Path;
+
+    /// For list views
+    pub const SMALL: u32 = 64;
+    /// For grid views
+    pub const MEDIUM: u32 = 256;
+    /// For now playing views
+    pub const LARGE: u32 = 600;
+    /// The sizes clients use
+    pub const SIZES: [u32; 3] = [SMALL, MEDIUM, LARGE];
+    /// Quality of JPEG thumbnails, from 1 to 100
+    pub const JPEG_QUALITY: u8 = 85;
+
+    #[derive(Clone, Copy, Debug, Default, PartialEq)]
+    pub enum Format {
+        #[default]
+        Jpeg,
+        /// Keeps transparency
+        Png,
+    }
+
+    impl Format {
+        fn file_type(&self) -> &'static str {
+            match self {
+                Format::Jpeg => "jpeg",
+                Format::Png => "png",
+            }
+        }
+
+        fn extension(&self) -> &'static str {
+            match self {
+                Format::Jpeg => crate::constants::file_extensions::image::JPEGEXTENSION,
+                Format::Png => crate::constants::file_extensions::image::PNGEXTENSION,
+            }
+        }
+    }
+
+    /// Creates a thumbnail of the coverart for each size
+    pub fn create_all(
+        coverart: &super::CoverArt,
+        sizes: &[u32],
+        format: Format,
+    ) -> Result<Vec<super::CoverArt>, crate::Error> {
+        let image = decode(coverart)?;
+        sizes
+            .iter()
+            .map(|size| from_image(coverart, &image, *size, format))
+            .collect()
+    }
+
+    /// Creates a thumbnail of the coverart fitting within a square of the given size. The aspect
+    /// ratio is kept and images smaller than the size are not enlarged. The thumbnail is linked
+    /// to the coverart through its original_id and is named after the coverart's filename, such
+    /// as `coverart-256.jpeg`
+    pub fn create(
+        coverart: &super::CoverArt,
+        size: u32,
+        format: Format,
+    ) -> Result<super::CoverArt, crate::Error> {
+        let image = decode(coverart)?;
+        from_image(coverart, &image, size, format)
+    }
+
+    /// Decodes the coverart's data, or the file at the coverart's path when not loaded
+    fn decode(coverart: &super::CoverArt) -> Result<image::DynamicImage, crate::Error> {
+        let loaded;
+        let data = if coverart.data.is_empty() {
+            loaded = super::io::to_data(coverart)?;
+            &loaded
+        } else {
+            &coverart.data
+        };
+
+        match image::load_from_memory(data) {
+            Ok(image) => Ok(image),
+            Err(image::ImageError::IoError(err)) => Err(crate::Error::Io(err)),
+            Err(_) => Err(crate::Error::InvalidImage),
+        }
+    }
+
+    fn from_image(
+        coverart: &super::CoverArt,
+        image: &image::DynamicImage,
+        size: u32,
+        format: Format,
+    ) -> Result<super::CoverArt, crate::Error> {
+        if size == 0 {
+            return Err(crate::Error::InvalidImage);
+        }
+
+        let resized = if image.width() > size || image.height() > size {
+            image.resize(size, size, image::imageops::FilterType::Lanczos3)
+        } else {
+            image.clone()
+        };
+
+        let data = encode(&resized, format)?;
+        let filename = if coverart.filename.is_empty() {
+            String::new()
+        } else {
+            let stem = match Path::new(&coverart.filename).file_stem() {
+                Some(stem) => stem.to_string_lossy().into_owned(),
+                None => coverart.filename.clone(),
+            };
+            format!("{stem}-{size}{}", format.extension())
+        };
+
+        Ok(super::CoverArt {
+            title: coverart.title.clone(),
+            directory: coverart.directory.clone(),
+            filename,
+            file_type: String::from(format.file_type()),
+            song_id: coverart.song_id,
+            checksum: crate::util::checksum::sha256(&data),
+            width: resized.width() as i32,
+            height: resized.height() as i32,
+            mime_type: String::from(match format {
+                Format::Jpeg => crate::constants::mime::JPEG,
+                Format::Png => crate::constants::mime::PNG,
+            }),
+            size: data.len() as i64,
+            original_id: coverart.id,
+            data,
+            ..Default::default()
+        })
+    }
+
+    fn encode(image: &image::DynamicImage, format: Format) -> Result<Vec<u8>, crate::Error> {
+        let mut data: Vec<u8> = Vec::new();
+        let result = match format {
+            // JPEG has no alpha channel
+            Format::Jpeg => image::DynamicImage::ImageRgb8(image.to_rgb8()).write_with_encoder(
+                image::codecs::jpeg::JpegEncoder::new_with_quality(&mut data, JPEG_QUALITY),
+            ),
+            Format::Png => image.write_with_encoder(image::codecs::png::PngEncoder::new(&mut data)),
+        };
+
+        match result {
+            Ok(_) => Ok(data),
+            Err(image::ImageError::IoError(err)) => Err(crate::Error::Io(err)),
+            Err(_) => Err(crate::Error::InvalidImage),
+        }
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,164 @@
+//! Errors returned by the crate
+
+/// Errors returned by the crate
+#[derive(Debug)]
+pub enum Error {
+    IdNotInitialized,
+    DirectoryNotInitialized,
+    FilenameNotInitialized,
+    LastCharacterInDirectory,
+    /// The filename is absolute, contains separators or refers to a parent directory
+    InvalidFilename,
+    /// Every generated filename was already taken
+    FilenameCollision,
+    /// A filename template has an unknown placeholder or unbalanced braces
+    InvalidTemplate,
+    UnsupportedMusicType,
+    UnsupportedCoverArtType,
+    /// The data is not FLAC, MP3 or WAV audio
+    UnsupportedAudioFormat,
+    NotAFlacFile,
+    /// A FLAC metadata block is truncated or malformed
+    InvalidFlacMetadata,
+    /// The data of a FLAC metadata block, such as an embedded picture, is 16 MiB or larger
+    MetadataBlockTooLarge,
+    InvalidId3Tag,
+    /// The ID3 tag is neither ID3v2.3 nor ID3v2.4
+    UnsupportedId3Version,
+    /// The RIFF header or a chunk of the WAV file is malformed
+    InvalidWavFile,
+    /// No MPEG audio frames were found in the stream
+    InvalidMpegStream,
+    /// The file's data does not match the extension of its filename
+    DataDoesNotMatchExtension,
+    FileEmpty,
+    FileNotFound,
+    FileAlreadyExists,
+    RangeNotSatisfiable,
+    ChecksumNotSet,
+    InvalidChecksum,
+    NoEmbeddedCoverArt,
+    CoverArtDataEmpty,
+    /// The image is not a PNG, JPEG, WebP or GIF, or its headers are malformed
+    InvalidImage,
+    ImageTooLarge,
+    ImageNotSquare,
+    /// The key encoding can not be used with the signing algorithm, such as a PEM key for HS256
+    UnsupportedKeyFormat,
+    DuplicateKeyId,
+    /// The `kid` header of the token is missing or names a key that is not in the KeySet
+    UnknownKeyId,
+    /// No key of the KeySet is signing tokens at the current time
+    NoSigningKey,
+    /// A refresh token was presented after it had been rotated or revoked
+    RefreshTokenReused,
+    /// The `jti` of the token is in the revocation store
+    TokenRevoked,
+    /// The token is malformed, its signature does not match or a required claim is missing
+    InvalidToken,
+    TokenExpired,
+    /// The token's not before or issued at time is in the future
+    TokenNotYetValid,
+    InvalidTokenIssuer,
+    InvalidTokenAudience,
+    /// Reading or writing a file failed
+    Io(std::io::Error),
+    Serde(serde_json::Error),
+    Jose(josekit::JoseError),
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        use crate::constants::error;
+
+        match self {
+            Error::IdNotInitialized => f.write_str(error::ID_NOT_INITIALIZED),
+            Error::DirectoryNotInitialized => f.write_str(error::DIRECTORY_NOT_INITIALIZED),
+            Error::FilenameNotInitialized => f.write_str(error::FILENAME_NOT_INITIALIZED),
+            Error::LastCharacterInDirectory => f.write_str(error::LAST_CHARACTER_IN_DIRECTORY),
+            Error::InvalidFilename => f.write_str(error::INVALID_FILENAME),
+            Error::FilenameCollision => f.write_str(error::FILENAME_COLLISION),
+            Error::InvalidTemplate => f.write_str(error::INVALID_TEMPLATE),
+            Error::UnsupportedMusicType => f.write_str(error::UNSUPPORTED_MUSIC_TYPE),
+            Error::UnsupportedCoverArtType => f.write_str(error::UNSUPPORTED_COVERART_FILE_TYPE),
+            Error::UnsupportedAudioFormat => f.write_str(error::UNSUPPORTED_AUDIO_FORMAT),
+            Error::NotAFlacFile => f.write_str(error::NOT_A_FLAC_FILE),
+            Error::InvalidFlacMetadata => f.write_str(error::INVALID_FLAC_METADATA),
+            Error::MetadataBlockTooLarge => f.write_str(error::METADATA_BLOCK_TOO_LARGE),
+            Error::InvalidId3Tag => f.write_str(error::INVALID_ID3_TAG),
+            Error::UnsupportedId3Version => f.write_str(error::UNSUPPORTED_ID3_VERSION),
+            Error::InvalidWavFile => f.write_str(error::INVALID_WAV_FILE),
+            Error::InvalidMpegStream => f.write_str(error::INVALID_MPEG_STREAM),
+            Error::DataDoesNotMatchExtension => f.write_str(error::DATA_DOES_NOT_MATCH_EXTENSION),
+            Error::FileEmpty => f.write_str(error::FILE_EMPTY),
+            Error::FileNotFound => f.write_str(error::FILE_DOES_NOT_EXIST),
+            Error::FileAlreadyExists => f.write_str(error::FILE_ALREADY_EXISTS),
+            Error::RangeNotSatisfiable => f.write_str(error::RANGE_NOT_SATISFIABLE),
+            Error::ChecksumNotSet => f.write_str(error::CHECKSUM_NOT_SET),
+            Error::InvalidChecksum => f.write_str(error::INVALID_CHECKSUM),
+            Error::NoEmbeddedCoverArt => f.write_str(error::NO_EMBEDDED_COVERART),
+            Error::CoverArtDataEmpty => f.write_str(error::COVERART_DATA_EMPTY),
+            Error::InvalidImage => f.write_str(error::INVALID_IMAGE),
+            Error::ImageTooLarge => f.write_str(error::IMAGE_TOO_LARGE),
+            Error::ImageNotSquare => f.write_str(error::IMAGE_NOT_SQUARE),
+            Error::UnsupportedKeyFormat => f.write_str(error::UNSUPPORTED_KEY_FORMAT),
+            Error::DuplicateKeyId => f.write_str(error::DUPLICATE_KEY_ID),
+            Error::UnknownKeyId => f.write_str(error::UNKNOWN_KEY_ID),
+            Error::NoSigningKey => f.write_str(error::NO_SIGNING_KEY),
+            Error::RefreshTokenReused => f.write_str(error::REFRESH_TOKEN_REUSED),
+            Error::TokenRevoked => f.write_str(error::TOKEN_REVOKED),
+            Error::InvalidToken => f.write_str(error::INVALID_TOKEN),
+            Error::TokenExpired => f.write_str(error::TOKEN_EXPIRED),
+            Error::TokenNotYetValid => f.write_str(error::TOKEN_NOT_YET_VALID),
+            Error::InvalidTokenIssuer => f.write_str(error::INVALID_TOKEN_ISSUER),
+            Error::InvalidTokenAudience => f.write_str(error::INVALID_TOKEN_AUDIENCE),
+            Error::Io(err) => write!(f, "{err}"),
+            Error::Serde(err) => write!(f, "{err}"),
+            Error::Jose(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io(err) => Some(err),
+            Error::Serde(err) => Some(err),
+            Error::Jose(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for Error {
+    fn from(err: std::io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        Error::Serde(err)
+    }
+}
+
+impl From<josekit::JoseError> for Error {
+    fn from(err: josekit::JoseError) -> Self {
+        Error::Jose(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_display() {
+        assert_eq!(
+            crate::constants::error::DIRECTORY_NOT_INITIALIZED,
+            super::Error::DirectoryNotInitialized.to_string()
+        );
+
+        let err = super::Error::from(std::io::Error::other("disk is full"));
+        assert_eq!("disk is full", err.to_string());
+        assert!(std::error::Error::source(&err).is_some());
+    }
+}
@@ -1,14 +1,20 @@
 pub mod access_level;
 pub mod album;
+pub mod audio_properties;
 pub mod constants;
 pub mod coverart;
+pub mod error;
 pub mod login_result;
+pub mod metadata;
 pub mod song;
+pub mod storage;
 pub mod token;
 pub mod types;
 pub mod user;
 pub mod util;
 
+pub use error::Error;
+
 pub mod init {
     pub fn is_id_valid(num: &i32) -> bool {
         *num > 0
@@ -22,6 +28,10 @@ pub mod init {
         *num == 0
     }
 
+    pub fn is_size_not_set(num: &i64) -> bool {
+        *num == 0
+    }
+
     pub fn is_dur_not_set(num: &i32) -> bool {
         *num == 0
     }
@@ -10,6 +10,9 @@ pub struct LoginResult {
     #[serde(alias = "token_type")]
     pub token_type: String,
     pub expiration: i64,
+    /// Opaque token exchanged for a new access token once this one expires
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub refresh_token: String,
 }
 
 impl LoginResult {
@@ -0,0 +1,778 @@
+//! FLAC metadata blocks. Covers STREAMINFO, VORBIS_COMMENT and PICTURE
+
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// Every FLAC stream begins with these bytes
+pub const FLAC_MARKER: &[u8; 4] = b"fLaC";
+
+/// Identifiers of the metadata block types
+pub mod block_type {
+    pub const STREAMINFO: u8 = 0;
+    pub const PADDING: u8 = 1;
+    pub const APPLICATION: u8 = 2;
+    pub const SEEKTABLE: u8 = 3;
+    pub const VORBIS_COMMENT: u8 = 4;
+    pub const CUESHEET: u8 = 5;
+    pub const PICTURE: u8 = 6;
+}
+
+/// Vorbis comment field names used to fill a Song
+pub mod field {
+    pub const TITLE: &str = "TITLE";
+    pub const ARTIST: &str = "ARTIST";
+    pub const ALBUM: &str = "ALBUM";
+    pub const ALBUM_ARTIST: &str = "ALBUMARTIST";
+    pub const ALBUM_ARTIST_ALT: &str = "ALBUM ARTIST";
+    pub const GENRE: &str = "GENRE";
+    pub const DATE: &str = "DATE";
+    pub const YEAR: &str = "YEAR";
+    pub const TRACK_NUMBER: &str = "TRACKNUMBER";
+    pub const TRACK_TOTAL: &str = "TRACKTOTAL";
+    pub const TOTAL_TRACKS: &str = "TOTALTRACKS";
+    pub const DISC_NUMBER: &str = "DISCNUMBER";
+    pub const DISC_TOTAL: &str = "DISCTOTAL";
+    pub const TOTAL_DISCS: &str = "TOTALDISCS";
+}
+
+/// Picture types of a PICTURE block
+pub mod picture_type {
+    pub const OTHER: u32 = 0;
+    pub const FRONT_COVER: u32 = 3;
+    pub const BACK_COVER: u32 = 4;
+}
+
+/// Size of the header preceding the data of every metadata block
+const BLOCK_HEADER_LENGTH: usize = 4;
+/// Largest block data whose length fits in the 24 bits of the block header
+pub const MAX_BLOCK_LENGTH: usize = (1 << 24) - 1;
+/// Size of the STREAMINFO block data
+const STREAMINFO_LENGTH: usize = 34;
+/// Size of the padding added when the metadata outgrows the space available before the audio
+const DEFAULT_PADDING_LENGTH: usize = 4096;
+/// Vendor string used when a file has no VORBIS_COMMENT yet
+const DEFAULT_VENDOR: &str = "icarus_models";
+
+#[derive(Clone, Debug, Default)]
+pub struct MetadataBlock {
+    pub block_type: u8,
+    pub data: Vec<u8>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct StreamInfo {
+    pub min_block_size: u16,
+    pub max_block_size: u16,
+    pub min_frame_size: u32,
+    pub max_frame_size: u32,
+    pub sample_rate: u32,
+    pub channels: u8,
+    pub bits_per_sample: u8,
+    pub total_samples: u64,
+    /// MD5 signature of the unencoded audio data
+    pub md5: [u8; 16],
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct VorbisComment {
+    pub vendor: String,
+    pub comments: Vec<(String, String)>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Picture {
+    pub picture_type: u32,
+    pub mime_type: String,
+    pub description: String,
+    pub width: u32,
+    pub height: u32,
+    pub depth: u32,
+    /// Number of colors used by indexed-color pictures, 0 otherwise
+    pub colors: u32,
+    pub data: Vec<u8>,
+}
+
+impl MetadataBlock {
+    /// Serializes the block including its header. Data longer than `MAX_BLOCK_LENGTH` can not be
+    /// represented, which `update_blocks` checks before writing
+    pub fn to_bytes(&self, is_last: bool) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(BLOCK_HEADER_LENGTH + self.data.len());
+        let flag: u8 = if is_last { 0x80 } else { 0x00 };
+        let length = self.data.len() as u32;
+
+        bytes.push(flag | (self.block_type & 0x7F));
+        bytes.extend_from_slice(&length.to_be_bytes()[1..]);
+        bytes.extend_from_slice(&self.data);
+        bytes
+    }
+}
+
+impl StreamInfo {
+    pub fn parse(data: &[u8]) -> Result<StreamInfo, crate::Error> {
+        if data.len() < STREAMINFO_LENGTH {
+            return Err(crate::Error::InvalidFlacMetadata);
+        }
+
+        let mut md5 = [0u8; 16];
+        md5.copy_from_slice(&data[18..34]);
+
+        Ok(StreamInfo {
+            min_block_size: u16::from_be_bytes([data[0], data[1]]),
+            max_block_size: u16::from_be_bytes([data[2], data[3]]),
+            min_frame_size: u32::from_be_bytes([0, data[4], data[5], data[6]]),
+            max_frame_size: u32::from_be_bytes([0, data[7], data[8], data[9]]),
+            sample_rate: (u32::from(data[10]) << 12)
+                | (u32::from(data[11]) << 4)
+                | (u32::from(data[12]) >> 4),
+            channels: ((data[12] >> 1) & 0x07) + 1,
+            bits_per_sample: (((data[12] & 0x01) << 4) | (data[13] >> 4)) + 1,
+            total_samples: (u64::from(data[13] & 0x0F) << 32)
+                | u64::from(u32::from_be_bytes([data[14], data[15], data[16], data[17]])),
+            md5,
+        })
+    }
+
+    /// Duration of the stream in seconds
+    pub fn duration(&self) -> f64 {
+        if self.sample_rate == 0 {
+            0.0
+        } else {
+            self.total_samples as f64 / f64::from(self.sample_rate)
+        }
+    }
+}
+
+impl Picture {
+    pub fn parse(data: &[u8]) -> Result<Picture, crate::Error> {
+        let mut position: usize = 0;
+        let picture_type = read_be_u32(data, &mut position)?;
+        let mime_type = read_be_string(data, &mut position)?;
+        let description = read_be_string(data, &mut position)?;
+        let width = read_be_u32(data, &mut position)?;
+        let height = read_be_u32(data, &mut position)?;
+        let depth = read_be_u32(data, &mut position)?;
+        let colors = read_be_u32(data, &mut position)?;
+        let length = read_be_u32(data, &mut position)? as usize;
+
+        match data.get(position..position + length) {
+            Some(bytes) => Ok(Picture {
+                picture_type,
+                mime_type,
+                description,
+                width,
+                height,
+                depth,
+                colors,
+                data: bytes.to_vec(),
+            }),
+            None => Err(crate::Error::InvalidFlacMetadata),
+        }
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = Vec::new();
+        bytes.extend_from_slice(&self.picture_type.to_be_bytes());
+        bytes.extend_from_slice(&(self.mime_type.len() as u32).to_be_bytes());
+        bytes.extend_from_slice(self.mime_type.as_bytes());
+        bytes.extend_from_slice(&(self.description.len() as u32).to_be_bytes());
+        bytes.extend_from_slice(self.description.as_bytes());
+        bytes.extend_from_slice(&self.width.to_be_bytes());
+        bytes.extend_from_slice(&self.height.to_be_bytes());
+        bytes.extend_from_slice(&self.depth.to_be_bytes());
+        bytes.extend_from_slice(&self.colors.to_be_bytes());
+        bytes.extend_from_slice(&(self.data.len() as u32).to_be_bytes());
+        bytes.extend_from_slice(&self.data);
+        bytes
+    }
+}
+
+impl VorbisComment {
+    pub fn parse(data: &[u8]) -> Result<VorbisComment, crate::Error> {
+        let mut position: usize = 0;
+        let vendor = read_le_string(data, &mut position)?;
+        let count = read_le_u32(data, &mut position)?;
+        let mut comments: Vec<(String, String)> = Vec::new();
+
+        for _ in 0..count {
+            let comment = read_le_string(data, &mut position)?;
+            if let Some((key, value)) = comment.split_once('=') {
+                comments.push((String::from(key), String::from(value)));
+            }
+        }
+
+        Ok(VorbisComment { vendor, comments })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = Vec::new();
+        bytes.extend_from_slice(&(self.vendor.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(self.vendor.as_bytes());
+        bytes.extend_from_slice(&(self.comments.len() as u32).to_le_bytes());
+
+        for (key, value) in &self.comments {
+            let comment = format!("{key}={value}");
+            bytes.extend_from_slice(&(comment.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(comment.as_bytes());
+        }
+
+        bytes
+    }
+
+    /// Gets the first value of a field. Field names are case insensitive
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.comments
+            .iter()
+            .find(|(k, _)| k.eq_ignore_ascii_case(key))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Replaces every value of a field. Empty values remove the field
+    pub fn set(&mut self, key: &str, value: &str) {
+        self.remove(key);
+        if !value.is_empty() {
+            self.comments.push((String::from(key), String::from(value)));
+        }
+    }
+
+    pub fn remove(&mut self, key: &str) {
+        self.comments.retain(|(k, _)| !k.eq_ignore_ascii_case(key));
+    }
+}
+
+/// Reads the metadata blocks at the start of a FLAC stream. Reading stops after the last block,
+/// leaving the reader at the first audio frame
+pub fn read_blocks<R: Read>(reader: &mut R) -> Result<Vec<MetadataBlock>, crate::Error> {
+    let mut marker = [0u8; 4];
+    super::read_exact(reader, &mut marker, crate::Error::NotAFlacFile)?;
+    if &marker != FLAC_MARKER {
+        return Err(crate::Error::NotAFlacFile);
+    }
+
+    let mut blocks: Vec<MetadataBlock> = Vec::new();
+    loop {
+        let mut header = [0u8; BLOCK_HEADER_LENGTH];
+        super::read_exact(reader, &mut header, crate::Error::InvalidFlacMetadata)?;
+
+        let is_last = header[0] & 0x80 != 0;
+        let length = u32::from_be_bytes([0, header[1], header[2], header[3]]) as usize;
+        let mut data = vec![0u8; length];
+        super::read_exact(reader, &mut data, crate::Error::InvalidFlacMetadata)?;
+
+        blocks.push(MetadataBlock {
+            block_type: header[0] & 0x7F,
+            data,
+        });
+
+        if is_last {
+            break;
+        }
+    }
+
+    Ok(blocks)
+}
+
+/// Gets the size in bytes of the marker and metadata blocks preceding the audio frames
+pub fn header_length(blocks: &[MetadataBlock]) -> usize {
+    blocks.iter().fold(FLAC_MARKER.len(), |length, block| {
+        length + BLOCK_HEADER_LENGTH + block.data.len()
+    })
+}
+
+/// Gets the STREAMINFO of the metadata blocks
+pub fn stream_info(blocks: &[MetadataBlock]) -> Result<StreamInfo, crate::Error> {
+    match blocks
+        .iter()
+        .find(|block| block.block_type == block_type::STREAMINFO)
+    {
+        Some(block) => StreamInfo::parse(&block.data),
+        None => Err(crate::Error::InvalidFlacMetadata),
+    }
+}
+
+/// Gets the VORBIS_COMMENT of the metadata blocks, if there is one
+pub fn vorbis_comment(blocks: &[MetadataBlock]) -> Result<Option<VorbisComment>, crate::Error> {
+    match blocks
+        .iter()
+        .find(|block| block.block_type == block_type::VORBIS_COMMENT)
+    {
+        Some(block) => Ok(Some(VorbisComment::parse(&block.data)?)),
+        None => Ok(None),
+    }
+}
+
+/// Computes the duration in seconds of a FLAC stream from its STREAMINFO
+pub fn duration<R: Read>(reader: &mut R) -> Result<f64, crate::Error> {
+    let blocks = read_blocks(reader)?;
+    Ok(stream_info(&blocks)?.duration())
+}
+
+/// Gets the technical properties of a FLAC stream. The bitrate is averaged over the audio frames
+pub fn audio_properties<R: Read + Seek>(
+    reader: &mut R,
+) -> Result<crate::audio_properties::AudioProperties, crate::Error> {
+    reader.seek(SeekFrom::Start(0))?;
+    let blocks = read_blocks(reader)?;
+    let info = stream_info(&blocks)?;
+    let length = reader
+        .seek(SeekFrom::End(0))?
+        .saturating_sub(header_length(&blocks) as u64);
+
+    let duration = info.duration();
+    let bitrate = if duration > 0.0 {
+        (length as f64 * 8.0 / duration / 1000.0).round() as i32
+    } else {
+        0
+    };
+
+    Ok(crate::audio_properties::AudioProperties {
+        codec: String::from(crate::audio_properties::codec::FLAC),
+        sample_rate: info.sample_rate as i32,
+        bit_depth: i32::from(info.bits_per_sample),
+        channels: i32::from(info.channels),
+        bitrate,
+    })
+}
+
+/// Fills the song's fields from the metadata of a FLAC stream
+pub fn read_song_metadata<R: Read>(
+    reader: &mut R,
+    song: &mut crate::song::Song,
+) -> Result<(), crate::Error> {
+    let blocks = read_blocks(reader)?;
+    let info = stream_info(&blocks)?;
+    song.duration = info.duration().round() as i32;
+
+    if let Some(comment) = vorbis_comment(&blocks)? {
+        apply_comment(&comment, song);
+    }
+
+    Ok(())
+}
+
+/// Copies the fields of a Vorbis comment over to a song. Fields missing from the comment are
+/// left untouched
+pub fn apply_comment(comment: &VorbisComment, song: &mut crate::song::Song) {
+    if let Some(title) = comment.get(field::TITLE) {
+        song.title = String::from(title);
+    }
+    if let Some(artist) = comment.get(field::ARTIST) {
+        song.artist = String::from(artist);
+    }
+    if let Some(album) = comment.get(field::ALBUM) {
+        song.album = String::from(album);
+    }
+    if let Some(album_artist) = comment
+        .get(field::ALBUM_ARTIST)
+        .or_else(|| comment.get(field::ALBUM_ARTIST_ALT))
+    {
+        song.album_artist = String::from(album_artist);
+    }
+    if let Some(genre) = comment.get(field::GENRE) {
+        song.genre = String::from(genre);
+    }
+    if let Some(date) = comment
+        .get(field::DATE)
+        .or_else(|| comment.get(field::YEAR))
+    {
+        song.year = super::parse_year(date);
+    }
+    if let Some(track) = comment.get(field::TRACK_NUMBER) {
+        song.track = super::parse_number(track);
+        song.track_count = super::parse_total(track);
+    }
+    if let Some(track_count) = comment
+        .get(field::TRACK_TOTAL)
+        .or_else(|| comment.get(field::TOTAL_TRACKS))
+    {
+        song.track_count = super::parse_number(track_count);
+    }
+    if let Some(disc) = comment.get(field::DISC_NUMBER) {
+        song.disc = super::parse_number(disc);
+        song.disc_count = super::parse_total(disc);
+    }
+    if let Some(disc_count) = comment
+        .get(field::DISC_TOTAL)
+        .or_else(|| comment.get(field::TOTAL_DISCS))
+    {
+        song.disc_count = super::parse_number(disc_count);
+    }
+}
+
+/// Builds a Vorbis comment from the song's fields. Fields of the existing comment that the song
+/// does not manage are preserved
+pub fn comment_from_song(
+    existing: Option<VorbisComment>,
+    song: &crate::song::Song,
+) -> VorbisComment {
+    let mut comment = existing.unwrap_or_else(|| VorbisComment {
+        vendor: String::from(DEFAULT_VENDOR),
+        comments: Vec::new(),
+    });
+
+    comment.set(field::TITLE, &song.title);
+    comment.set(field::ARTIST, &song.artist);
+    comment.set(field::ALBUM, &song.album);
+    comment.remove(field::ALBUM_ARTIST_ALT);
+    comment.set(field::ALBUM_ARTIST, &song.album_artist);
+    comment.set(field::GENRE, &song.genre);
+    // The date is left alone when the song's year is unknown, and a full date such as
+    // "2025-03-22" is kept as long as the song's year still matches it
+    if song.year > 0 {
+        let date = match comment
+            .get(field::DATE)
+            .or_else(|| comment.get(field::YEAR))
+        {
+            Some(date) if super::parse_year(date) == song.year => String::from(date),
+            _ => song.year.to_string(),
+        };
+        comment.remove(field::YEAR);
+        comment.set(field::DATE, &date);
+    }
+    comment.set(field::TRACK_NUMBER, &number_value(song.track));
+    comment.remove(field::TOTAL_TRACKS);
+    comment.set(field::TRACK_TOTAL, &number_value(song.track_count));
+    comment.set(field::DISC_NUMBER, &number_value(song.disc));
+    comment.remove(field::TOTAL_DISCS);
+    comment.set(field::DISC_TOTAL, &number_value(song.disc_count));
+
+    comment
+}
+
+/// Serializes the marker and metadata blocks. When the blocks fit within `available` bytes the
+/// padding is sized so that the result is exactly `available` bytes long, which allows the
+/// header to be overwritten without moving the audio frames
+pub fn render_header(blocks: &[MetadataBlock], available: usize) -> Vec<u8> {
+    let mut blocks: Vec<MetadataBlock> = blocks
+        .iter()
+        .filter(|block| block.block_type != block_type::PADDING)
+        .cloned()
+        .collect();
+    let needed = header_length(&blocks);
+
+    if needed == available {
+        // Fits exactly, no padding needed
+    } else if needed + BLOCK_HEADER_LENGTH <= available {
+        blocks.push(MetadataBlock {
+            block_type: block_type::PADDING,
+            data: vec![0u8; available - needed - BLOCK_HEADER_LENGTH],
+        });
+    } else {
+        blocks.push(MetadataBlock {
+            block_type: block_type::PADDING,
+            data: vec![0u8; DEFAULT_PADDING_LENGTH],
+        });
+    }
+
+    let mut header: Vec<u8> = FLAC_MARKER.to_vec();
+    let last_index = blocks.len() - 1;
+    for (index, block) in blocks.iter().enumerate() {
+        header.extend_from_slice(&block.to_bytes(index == last_index));
+    }
+
+    header
+}
+
+/// Rewrites the VORBIS_COMMENT of a FLAC file from the song's fields. The audio frames and the
+/// other metadata blocks are preserved
+pub fn write_song_metadata<S: crate::storage::Storage>(
+    storage: &S,
+    key: &Path,
+    song: &crate::song::Song,
+) -> Result<(), crate::Error> {
+    update_blocks(storage, key, |blocks| {
+        let comment = comment_from_song(vorbis_comment(blocks)?, song);
+        let comment_block = MetadataBlock {
+            block_type: block_type::VORBIS_COMMENT,
+            data: comment.to_bytes(),
+        };
+        match blocks
+            .iter()
+            .position(|block| block.block_type == block_type::VORBIS_COMMENT)
+        {
+            Some(index) => blocks[index] = comment_block,
+            None => blocks.insert(1.min(blocks.len()), comment_block),
+        }
+
+        Ok(())
+    })
+}
+
+/// Gets the PICTURE blocks of the metadata blocks
+pub fn pictures(blocks: &[MetadataBlock]) -> Result<Vec<Picture>, crate::Error> {
+    blocks
+        .iter()
+        .filter(|block| block.block_type == block_type::PICTURE)
+        .map(|block| Picture::parse(&block.data))
+        .collect()
+}
+
+/// Gets the picture that best represents the cover art. The front cover is preferred, otherwise
+/// the first picture is used
+pub fn cover_picture(blocks: &[MetadataBlock]) -> Result<Option<Picture>, crate::Error> {
+    let mut pictures = pictures(blocks)?;
+    match pictures
+        .iter()
+        .position(|picture| picture.picture_type == picture_type::FRONT_COVER)
+    {
+        Some(index) => Ok(Some(pictures.swap_remove(index))),
+        None if pictures.is_empty() => Ok(None),
+        None => Ok(Some(pictures.swap_remove(0))),
+    }
+}
+
+/// Embeds a picture into a FLAC file. A picture of the same type is replaced
+pub fn write_picture<S: crate::storage::Storage>(
+    storage: &S,
+    key: &Path,
+    picture: &Picture,
+) -> Result<(), crate::Error> {
+    update_blocks(storage, key, |blocks| {
+        let mut replaced = false;
+        for block in blocks.iter_mut() {
+            if block.block_type == block_type::PICTURE
+                && Picture::parse(&block.data)?.picture_type == picture.picture_type
+            {
+                if replaced {
+                    block.block_type = block_type::PADDING;
+                } else {
+                    block.data = picture.to_bytes();
+                    replaced = true;
+                }
+            }
+        }
+
+        if !replaced {
+            blocks.push(MetadataBlock {
+                block_type: block_type::PICTURE,
+                data: picture.to_bytes(),
+            });
+        }
+
+        Ok(())
+    })
+}
+
+/// Applies changes to the metadata blocks of a FLAC file. The audio frames are preserved. If the
+/// changed metadata fits in the space taken up by the old metadata and its padding, only the
+/// header is overwritten, otherwise the file is rewritten atomically
+pub fn update_blocks<S, F>(storage: &S, key: &Path, update: F) -> Result<(), crate::Error>
+where
+    S: crate::storage::Storage,
+    F: FnOnce(&mut Vec<MetadataBlock>) -> Result<(), crate::Error>,
+{
+    let mut blocks = read_blocks(&mut std::io::BufReader::new(storage.open(key)?))?;
+    let original_length = header_length(&blocks);
+
+    update(&mut blocks)?;
+    if blocks
+        .iter()
+        .any(|block| block.data.len() > MAX_BLOCK_LENGTH)
+    {
+        return Err(crate::Error::MetadataBlockTooLarge);
+    }
+
+    let header = render_header(&blocks, original_length);
+    super::replace_header(storage, key, &header, original_length as u64)
+}
+
+fn number_value(number: i32) -> String {
+    if number > 0 {
+        number.to_string()
+    } else {
+        String::new()
+    }
+}
+
+fn read_le_u32(data: &[u8], position: &mut usize) -> Result<u32, crate::Error> {
+    match data.get(*position..*position + 4) {
+        Some(bytes) => {
+            *position += 4;
+            Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        }
+        None => Err(crate::Error::InvalidFlacMetadata),
+    }
+}
+
+fn read_le_string(data: &[u8], position: &mut usize) -> Result<String, crate::Error> {
+    let length = read_le_u32(data, position)? as usize;
+    match data.get(*position..*position + length) {
+        Some(bytes) => {
+            *position += length;
+            Ok(String::from_utf8_lossy(bytes).into_owned())
+        }
+        None => Err(crate::Error::InvalidFlacMetadata),
+    }
+}
+
+fn read_be_u32(data: &[u8], position: &mut usize) -> Result<u32, crate::Error> {
+    match data.get(*position..*position + 4) {
+        Some(bytes) => {
+            *position += 4;
+            Ok(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        }
+        None => Err(crate::Error::InvalidFlacMetadata),
+    }
+}
+
+fn read_be_string(data: &[u8], position: &mut usize) -> Result<String, crate::Error> {
+    let length = read_be_u32(data, position)? as usize;
+    match data.get(*position..*position + length) {
+        Some(bytes) => {
+            *position += length;
+            Ok(String::from_utf8_lossy(bytes).into_owned())
+        }
+        None => Err(crate::Error::InvalidFlacMetadata),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_vorbis_comment_round_trip() {
+        let mut comment = VorbisComment {
+            vendor: String::from("icarus"),
+            comments: Vec::new(),
+        };
+        comment.set(field::TITLE, "Song");
+        comment.set(field::TRACK_NUMBER, "2/9");
+
+        let parsed = VorbisComment::parse(&comment.to_bytes()).unwrap();
+        assert_eq!("icarus", parsed.vendor);
+        assert_eq!(Some("Song"), parsed.get("title"));
+        assert_eq!(Some("2/9"), parsed.get(field::TRACK_NUMBER));
+    }
+
+    #[test]
+    fn test_render_header_reuses_padding() {
+        let blocks = vec![
+            MetadataBlock {
+                block_type: block_type::STREAMINFO,
+                data: vec![0u8; STREAMINFO_LENGTH],
+            },
+            MetadataBlock {
+                block_type: block_type::PADDING,
+                data: vec![0u8; 100],
+            },
+        ];
+        let available = header_length(&blocks);
+
+        let header = render_header(&blocks[..1], available);
+        assert_eq!(available, header.len());
+
+        let large = vec![
+            blocks[0].clone(),
+            MetadataBlock {
+                block_type: block_type::VORBIS_COMMENT,
+                data: vec![0u8; 200],
+            },
+        ];
+        let header = render_header(&large, available);
+        assert!(header.len() > available);
+
+        let parsed = read_blocks(&mut header.as_slice()).unwrap();
+        assert_eq!(header.len(), header_length(&parsed));
+        assert_eq!(block_type::PADDING, parsed.last().unwrap().block_type);
+    }
+
+    #[test]
+    fn test_picture_round_trip() {
+        let picture = Picture {
+            picture_type: picture_type::FRONT_COVER,
+            mime_type: String::from("image/png"),
+            description: String::from("Cover"),
+            width: 600,
+            height: 600,
+            depth: 24,
+            colors: 0,
+            data: vec![1, 2, 3, 4],
+        };
+
+        let parsed = Picture::parse(&picture.to_bytes()).unwrap();
+        assert_eq!(picture.mime_type, parsed.mime_type);
+        assert_eq!(picture.description, parsed.description);
+        assert_eq!(picture.width, parsed.width);
+        assert_eq!(picture.data, parsed.data);
+    }
+
+    #[test]
+    fn test_apply_comment() {
+        let comment = VorbisComment {
+            vendor: String::new(),
+            comments: vec![
+                (String::from("TRACKNUMBER"), String::from("3/12")),
+                (String::from("DISCNUMBER"), String::from("1")),
+                (String::from("DISCTOTAL"), String::from("2")),
+                (String::from("DATE"), String::from("2025-03-22")),
+            ],
+        };
+        let mut song = crate::song::Song::default();
+        apply_comment(&comment, &mut song);
+
+        assert_eq!(3, song.track);
+        assert_eq!(12, song.track_count);
+        assert_eq!(1, song.disc);
+        assert_eq!(2, song.disc_count);
+        assert_eq!(2025, song.year);
+    }
+
+    #[test]
+    fn test_comment_from_song_keeps_date() {
+        let comment = VorbisComment {
+            vendor: String::new(),
+            comments: vec![(String::from("DATE"), String::from("2025-03-22"))],
+        };
+        let mut song = crate::song::Song::default();
+        apply_comment(&comment, &mut song);
+
+        let unchanged = comment_from_song(Some(comment.clone()), &song);
+        assert_eq!(Some("2025-03-22"), unchanged.get(field::DATE));
+
+        song.year = 2024;
+        let changed = comment_from_song(Some(comment), &song);
+        assert_eq!(Some("2024"), changed.get(field::DATE));
+    }
+
+    #[test]
+    fn test_comment_from_song_unknown_year_keeps_date() {
+        let comment = VorbisComment {
+            vendor: String::new(),
+            comments: vec![(String::from("DATE"), String::from("Spring 1999"))],
+        };
+        let song = crate::song::Song::default();
+
+        let written = comment_from_song(Some(comment), &song);
+        assert_eq!(Some("Spring 1999"), written.get(field::DATE));
+        assert_eq!(None, comment_from_song(None, &song).get(field::DATE));
+    }
+
+    #[test]
+    fn test_write_picture_too_large() {
+        use crate::storage::Storage;
+
+        let storage = crate::storage::MemoryStorage::new();
+        let path = Path::new("song.flac");
+        let blocks = vec![MetadataBlock {
+            block_type: block_type::STREAMINFO,
+            data: vec![0u8; STREAMINFO_LENGTH],
+        }];
+        let mut contents = render_header(&blocks, 0);
+        contents.extend_from_slice(b"audio");
+        storage
+            .put(path, &contents, crate::types::WriteMode::CreateNew)
+            .unwrap();
+
+        let picture = Picture {
+            picture_type: picture_type::FRONT_COVER,
+            mime_type: String::from("image/png"),
+            data: vec![0u8; MAX_BLOCK_LENGTH],
+            ..Default::default()
+        };
+        assert!(matches!(
+            write_picture(&storage, path, &picture),
+            Err(crate::Error::MetadataBlockTooLarge)
+        ));
+        assert_eq!(contents, storage.get(path).unwrap());
+    }
+}
@@ -0,0 +1,562 @@
+//! ID3v2.3 and ID3v2.4 tags. Found at the start of MP3 files and in the id3 chunk of WAV files
+
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// Every ID3v2 tag begins with these bytes
+pub const ID3_MARKER: &[u8; 3] = b"ID3";
+/// Size of the tag header, as well as of the optional footer
+pub const HEADER_LENGTH: usize = 10;
+/// Frame ids of ID3v2.3 and ID3v2.4 are four characters
+pub const FRAME_ID_LENGTH: usize = 4;
+
+/// Identifiers of the frames used to fill a Song
+pub mod frame_id {
+    pub const TITLE: &str = "TIT2";
+    pub const ARTIST: &str = "TPE1";
+    pub const ALBUM: &str = "TALB";
+    pub const ALBUM_ARTIST: &str = "TPE2";
+    pub const GENRE: &str = "TCON";
+    /// Year of ID3v2.3 tags
+    pub const YEAR: &str = "TYER";
+    /// Recording time of ID3v2.4 tags, replaces TYER
+    pub const RECORDING_TIME: &str = "TDRC";
+    pub const TRACK: &str = "TRCK";
+    pub const DISC: &str = "TPOS";
+    /// Length of the audio in milliseconds
+    pub const LENGTH: &str = "TLEN";
+}
+
+/// Text encodings of text frames
+mod encoding {
+    pub const ISO_8859_1: u8 = 0;
+    pub const UTF_16: u8 = 1;
+    pub const UTF_16_BE: u8 = 2;
+    pub const UTF_8: u8 = 3;
+}
+
+/// Header flags
+const FLAG_UNSYNCHRONISATION: u8 = 0x80;
+const FLAG_EXTENDED_HEADER: u8 = 0x40;
+const FLAG_FOOTER: u8 = 0x10;
+/// ID3v2.4 frame format flags that are undone while parsing
+const FRAME_FLAG_UNSYNCHRONISATION: u16 = 0x0002;
+const FRAME_FLAG_DATA_LENGTH_INDICATOR: u16 = 0x0001;
+/// ID3v2.4 frame format flags that leave the frame data opaque
+const FRAME_FLAGS_OPAQUE: u16 = 0x000C;
+/// Version written when a file has no tag yet
+const DEFAULT_MAJOR_VERSION: u8 = 4;
+/// Size of the padding added when a tag outgrows the space available before the audio
+const DEFAULT_PADDING_LENGTH: usize = 2048;
+
+#[derive(Clone, Debug, Default)]
+pub struct Frame {
+    pub id: String,
+    pub flags: u16,
+    pub data: Vec<u8>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Tag {
+    /// 3 for ID3v2.3, 4 for ID3v2.4
+    pub major_version: u8,
+    pub frames: Vec<Frame>,
+}
+
+impl Tag {
+    /// Parses a tag, starting at its header
+    pub fn parse(data: &[u8]) -> Result<Tag, crate::Error> {
+        if data.len() < HEADER_LENGTH || &data[..3] != ID3_MARKER {
+            return Err(crate::Error::InvalidId3Tag);
+        }
+
+        let major_version = data[3];
+        if major_version != 3 && major_version != 4 {
+            return Err(crate::Error::UnsupportedId3Version);
+        }
+
+        let flags = data[5];
+        let size = decode_syncsafe(&data[6..10]) as usize;
+        let body = match data.get(HEADER_LENGTH..HEADER_LENGTH + size) {
+            Some(body) => body,
+            None => {
+                return Err(crate::Error::InvalidId3Tag);
+            }
+        };
+        let body = if major_version == 3 && flags & FLAG_UNSYNCHRONISATION != 0 {
+            remove_unsynchronisation(body)
+        } else {
+            body.to_vec()
+        };
+
+        let mut position: usize = 0;
+        if flags & FLAG_EXTENDED_HEADER != 0 && body.len() >= 4 {
+            let length = if major_version == 3 {
+                u32::from_be_bytes([body[0], body[1], body[2], body[3]]) as usize + 4
+            } else {
+                decode_syncsafe(&body[..4]) as usize
+            };
+            position += length;
+        }
+
+        let mut frames: Vec<Frame> = Vec::new();
+        while position + HEADER_LENGTH <= body.len() && body[position] != 0 {
+            let header = &body[position..position + HEADER_LENGTH];
+            let id = String::from_utf8_lossy(&header[..4]).into_owned();
+            let length = if major_version == 3 {
+                u32::from_be_bytes([header[4], header[5], header[6], header[7]]) as usize
+            } else {
+                decode_syncsafe(&header[4..8]) as usize
+            };
+            let mut flags = u16::from_be_bytes([header[8], header[9]]);
+            position += HEADER_LENGTH;
+
+            let mut data = match body.get(position..position + length) {
+                Some(data) => data.to_vec(),
+                None => break,
+            };
+            position += length;
+
+            if major_version == 4 && flags & FRAME_FLAGS_OPAQUE == 0 {
+                if flags & FRAME_FLAG_DATA_LENGTH_INDICATOR != 0 && data.len() >= 4 {
+                    data.drain(..4);
+                }
+                if flags & FRAME_FLAG_UNSYNCHRONISATION != 0 {
+                    data = remove_unsynchronisation(&data);
+                }
+                flags &= !(FRAME_FLAG_DATA_LENGTH_INDICATOR | FRAME_FLAG_UNSYNCHRONISATION);
+            }
+
+            frames.push(Frame { id, flags, data });
+        }
+
+        Ok(Tag {
+            major_version,
+            frames,
+        })
+    }
+
+    /// Serializes the tag including its header. The frames are followed by `padding` zero bytes.
+    /// Fails when a frame id is not four bytes long
+    pub fn to_bytes(&self, padding: usize) -> Result<Vec<u8>, crate::Error> {
+        let mut body: Vec<u8> = Vec::new();
+        for frame in &self.frames {
+            if frame.id.len() != FRAME_ID_LENGTH {
+                return Err(crate::Error::InvalidId3Tag);
+            }
+            let length = frame.data.len() as u32;
+            body.extend_from_slice(frame.id.as_bytes());
+            if self.major_version == 3 {
+                body.extend_from_slice(&length.to_be_bytes());
+            } else {
+                body.extend_from_slice(&encode_syncsafe(length));
+            }
+            body.extend_from_slice(&frame.flags.to_be_bytes());
+            body.extend_from_slice(&frame.data);
+        }
+        body.resize(body.len() + padding, 0);
+
+        let mut bytes: Vec<u8> = Vec::with_capacity(HEADER_LENGTH + body.len());
+        bytes.extend_from_slice(ID3_MARKER);
+        bytes.push(self.major_version);
+        bytes.push(0);
+        bytes.push(0);
+        bytes.extend_from_slice(&encode_syncsafe(body.len() as u32));
+        bytes.extend_from_slice(&body);
+        Ok(bytes)
+    }
+
+    /// Gets the first value of a text frame
+    pub fn text(&self, id: &str) -> Option<String> {
+        self.frames
+            .iter()
+            .find(|frame| frame.id == id)
+            .map(|frame| decode_text(&frame.data))
+    }
+
+    /// Replaces a text frame. Empty values remove the frame
+    pub fn set_text(&mut self, id: &str, value: &str) {
+        let new_frame = Frame {
+            id: String::from(id),
+            flags: 0,
+            data: encode_text(value, self.major_version),
+        };
+
+        let index = self.frames.iter().position(|frame| frame.id == id);
+        self.remove(id);
+        if !value.is_empty() {
+            match index {
+                Some(index) => self.frames.insert(index, new_frame),
+                None => self.frames.push(new_frame),
+            }
+        }
+    }
+
+    pub fn remove(&mut self, id: &str) {
+        self.frames.retain(|frame| frame.id != id);
+    }
+}
+
+/// Gets the size of the tag at the start of the data, including header and footer. Returns None
+/// when the data does not start with a tag
+pub fn tag_length(header: &[u8]) -> Option<usize> {
+    if header.len() < HEADER_LENGTH || &header[..3] != ID3_MARKER {
+        return None;
+    }
+
+    let size = decode_syncsafe(&header[6..10]) as usize;
+    let footer = if header[5] & FLAG_FOOTER != 0 {
+        HEADER_LENGTH
+    } else {
+        0
+    };
+
+    Some(HEADER_LENGTH + size + footer)
+}
+
+/// Reads the tag at the start of a stream, if there is one. The reader is left after the tag.
+/// Tags of an unsupported version, such as ID3v2.2, are skipped as if there was no tag
+pub fn read_tag<R: Read + Seek>(reader: &mut R) -> Result<Option<Tag>, crate::Error> {
+    let start = reader.stream_position()?;
+    let mut header = [0u8; HEADER_LENGTH];
+    if reader.read_exact(&mut header).is_err() {
+        reader.seek(SeekFrom::Start(start))?;
+        return Ok(None);
+    }
+
+    match tag_length(&header) {
+        Some(length) => {
+            let mut data = header.to_vec();
+            data.resize(length, 0);
+            super::read_exact(
+                reader,
+                &mut data[HEADER_LENGTH..],
+                crate::Error::InvalidId3Tag,
+            )?;
+            match Tag::parse(&data) {
+                Ok(tag) => Ok(Some(tag)),
+                Err(crate::Error::UnsupportedId3Version) => Ok(None),
+                Err(err) => Err(err),
+            }
+        }
+        None => {
+            reader.seek(SeekFrom::Start(start))?;
+            Ok(None)
+        }
+    }
+}
+
+/// Fills the song's fields from the tag at the start of an MP3 stream
+pub fn read_song_metadata<R: Read + Seek>(
+    reader: &mut R,
+    song: &mut crate::song::Song,
+) -> Result<(), crate::Error> {
+    if let Some(tag) = read_tag(reader)? {
+        apply_tag(&tag, song);
+    }
+
+    Ok(())
+}
+
+/// Copies the frames of a tag over to a song. Frames missing from the tag are left untouched
+pub fn apply_tag(tag: &Tag, song: &mut crate::song::Song) {
+    if let Some(title) = tag.text(frame_id::TITLE) {
+        song.title = title;
+    }
+    if let Some(artist) = tag.text(frame_id::ARTIST) {
+        song.artist = artist;
+    }
+    if let Some(album) = tag.text(frame_id::ALBUM) {
+        song.album = album;
+    }
+    if let Some(album_artist) = tag.text(frame_id::ALBUM_ARTIST) {
+        song.album_artist = album_artist;
+    }
+    if let Some(genre) = tag.text(frame_id::GENRE) {
+        song.genre = strip_genre_reference(&genre);
+    }
+    if let Some(date) = tag
+        .text(frame_id::RECORDING_TIME)
+        .or_else(|| tag.text(frame_id::YEAR))
+    {
+        song.year = super::parse_year(&date);
+    }
+    if let Some(track) = tag.text(frame_id::TRACK) {
+        song.track = super::parse_number(&track);
+        song.track_count = super::parse_total(&track);
+    }
+    if let Some(disc) = tag.text(frame_id::DISC) {
+        song.disc = super::parse_number(&disc);
+        song.disc_count = super::parse_total(&disc);
+    }
+    if let Some(length) = tag.text(frame_id::LENGTH) {
+        let milliseconds = super::parse_number(&length);
+        if milliseconds > 0 {
+            song.duration = (f64::from(milliseconds) / 1000.0).round() as i32;
+        }
+    }
+}
+
+/// Builds a tag from the song's fields. Frames of the existing tag that the song does not manage
+/// are preserved
+pub fn tag_from_song(existing: Option<Tag>, song: &crate::song::Song) -> Tag {
+    let mut tag = existing.unwrap_or(Tag {
+        major_version: DEFAULT_MAJOR_VERSION,
+        frames: Vec::new(),
+    });
+
+    tag.set_text(frame_id::TITLE, &song.title);
+    tag.set_text(frame_id::ARTIST, &song.artist);
+    tag.set_text(frame_id::ALBUM, &song.album);
+    tag.set_text(frame_id::ALBUM_ARTIST, &song.album_artist);
+    tag.set_text(frame_id::GENRE, &song.genre);
+
+    let year = if song.year > 0 {
+        song.year.to_string()
+    } else {
+        String::new()
+    };
+    if tag.major_version == 3 {
+        tag.remove(frame_id::RECORDING_TIME);
+        tag.set_text(frame_id::YEAR, &year);
+    } else {
+        tag.remove(frame_id::YEAR);
+        tag.set_text(frame_id::RECORDING_TIME, &year);
+    }
+
+    tag.set_text(
+        frame_id::TRACK,
+        &position_value(song.track, song.track_count),
+    );
+    tag.set_text(frame_id::DISC, &position_value(song.disc, song.disc_count));
+
+    tag
+}
+
+/// Rewrites the tag at the start of an MP3 file from the song's fields. If the new tag fits in
+/// the space taken up by the old tag, only the tag is overwritten, otherwise the file is
+/// rewritten atomically
+pub fn write_song_metadata<S: crate::storage::Storage>(
+    storage: &S,
+    key: &Path,
+    song: &crate::song::Song,
+) -> Result<(), crate::Error> {
+    let mut reader = std::io::BufReader::new(storage.open(key)?);
+    let existing = read_tag(&mut reader)?;
+    let original_length = reader.stream_position()? as usize;
+
+    let tag = tag_from_song(existing, song);
+    let unpadded_length = tag.to_bytes(0)?.len();
+    let bytes = if unpadded_length <= original_length {
+        tag.to_bytes(original_length - unpadded_length)?
+    } else {
+        tag.to_bytes(DEFAULT_PADDING_LENGTH)?
+    };
+
+    super::replace_header(storage, key, &bytes, original_length as u64)
+}
+
+/// Decodes the value of a text frame. Only the first of multiple values is returned
+pub fn decode_text(data: &[u8]) -> String {
+    let (encoding, text) = match data.split_first() {
+        Some((encoding, text)) => (*encoding, text),
+        None => return String::new(),
+    };
+
+    let decoded = match encoding {
+        encoding::UTF_16 => decode_utf16(text, None),
+        encoding::UTF_16_BE => decode_utf16(text, Some(true)),
+        encoding::UTF_8 => String::from_utf8_lossy(text).into_owned(),
+        _ => text.iter().map(|&b| char::from(b)).collect(),
+    };
+
+    match decoded.split('\0').next() {
+        Some(value) => String::from(value),
+        None => decoded,
+    }
+}
+
+/// Encodes the value of a text frame. ID3v2.4 tags use UTF-8. ID3v2.3 tags use ISO-8859-1 when
+/// possible and UTF-16 otherwise
+pub fn encode_text(value: &str, major_version: u8) -> Vec<u8> {
+    let mut data: Vec<u8> = Vec::new();
+    if major_version != 3 {
+        data.push(encoding::UTF_8);
+        data.extend_from_slice(value.as_bytes());
+    } else if value.chars().all(|c| (c as u32) < 0x100) {
+        data.push(encoding::ISO_8859_1);
+        data.extend(value.chars().map(|c| c as u8));
+    } else {
+        data.push(encoding::UTF_16);
+        data.extend_from_slice(&[0xFF, 0xFE]);
+        for unit in value.encode_utf16() {
+            data.extend_from_slice(&unit.to_le_bytes());
+        }
+    }
+
+    data
+}
+
+fn decode_utf16(text: &[u8], big_endian: Option<bool>) -> String {
+    let (big_endian, text) = match (big_endian, text) {
+        (Some(big_endian), _) => (big_endian, text),
+        (None, [0xFE, 0xFF, rest @ ..]) => (true, rest),
+        (None, [0xFF, 0xFE, rest @ ..]) => (false, rest),
+        (None, _) => (false, text),
+    };
+
+    let units: Vec<u16> = text
+        .chunks_exact(2)
+        .map(|pair| {
+            if big_endian {
+                u16::from_be_bytes([pair[0], pair[1]])
+            } else {
+                u16::from_le_bytes([pair[0], pair[1]])
+            }
+        })
+        .collect();
+
+    String::from_utf16_lossy(&units)
+}
+
+/// Genres of older tags may reference ID3v1 genres, such as "(2)Country"
+fn strip_genre_reference(genre: &str) -> String {
+    if let Some(rest) = genre.strip_prefix('(')
+        && let Some((number, name)) = rest.split_once(')')
+        && number.chars().all(|c| c.is_ascii_digit())
+        && !name.is_empty()
+    {
+        return String::from(name);
+    }
+
+    String::from(genre)
+}
+
+fn position_value(number: i32, total: i32) -> String {
+    if number <= 0 {
+        String::new()
+    } else if total > 0 {
+        format!("{number}/{total}")
+    } else {
+        number.to_string()
+    }
+}
+
+pub fn decode_syncsafe(bytes: &[u8]) -> u32 {
+    bytes
+        .iter()
+        .take(4)
+        .fold(0u32, |value, &byte| (value << 7) | u32::from(byte & 0x7F))
+}
+
+pub fn encode_syncsafe(value: u32) -> [u8; 4] {
+    [
+        ((value >> 21) & 0x7F) as u8,
+        ((value >> 14) & 0x7F) as u8,
+        ((value >> 7) & 0x7F) as u8,
+        (value & 0x7F) as u8,
+    ]
+}
+
+/// Undoes the unsynchronisation scheme, which inserts a zero byte after every 0xFF
+fn remove_unsynchronisation(data: &[u8]) -> Vec<u8> {
+    let mut result: Vec<u8> = Vec::with_capacity(data.len());
+    let mut previous: u8 = 0;
+    for &byte in data {
+        if !(previous == 0xFF && byte == 0x00) {
+            result.push(byte);
+        }
+        previous = byte;
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_syncsafe() {
+        assert_eq!(257, decode_syncsafe(&encode_syncsafe(257)));
+        assert_eq!([0, 0, 2, 1], encode_syncsafe(257));
+    }
+
+    #[test]
+    fn test_text_encodings() {
+        assert_eq!("Song", decode_text(&encode_text("Song", 4)));
+        assert_eq!("Café", decode_text(&encode_text("Café", 3)));
+        assert_eq!("日本", decode_text(&encode_text("日本", 3)));
+        assert_eq!("One", decode_text(b"\x03One\0Two"));
+    }
+
+    #[test]
+    fn test_tag_round_trip() {
+        let song = crate::song::Song {
+            title: String::from("Title"),
+            track: 2,
+            track_count: 10,
+            year: 2020,
+            genre: String::from("Country"),
+            ..Default::default()
+        };
+        let tag = tag_from_song(None, &song);
+        let parsed = Tag::parse(&tag.to_bytes(16).unwrap()).unwrap();
+
+        let mut read = crate::song::Song::default();
+        apply_tag(&parsed, &mut read);
+        assert_eq!(song.title, read.title);
+        assert_eq!(2, read.track);
+        assert_eq!(10, read.track_count);
+        assert_eq!(2020, read.year);
+        assert_eq!(song.genre, read.genre);
+    }
+
+    #[test]
+    fn test_invalid_frame_id() {
+        let tag = Tag {
+            major_version: 4,
+            frames: vec![Frame {
+                id: String::from("TT2"),
+                flags: 0,
+                data: encode_text("Title", 4),
+            }],
+        };
+        assert!(matches!(tag.to_bytes(0), Err(crate::Error::InvalidId3Tag)));
+    }
+
+    #[test]
+    fn test_strip_genre_reference() {
+        assert_eq!("Country", strip_genre_reference("(2)Country"));
+        assert_eq!("(2)", strip_genre_reference("(2)"));
+        assert_eq!("Rock", strip_genre_reference("Rock"));
+    }
+
+    #[test]
+    fn test_skip_unsupported_version() {
+        use crate::storage::Storage;
+
+        // An ID3v2.2 tag holding a single TT2 frame, then the audio
+        let data: &[u8] = b"ID3\x02\x00\x00\x00\x00\x00\x0CTT2\x00\x00\x06\x00Titleaudio";
+
+        let mut reader = std::io::Cursor::new(data);
+        let mut song = crate::song::Song::default();
+        read_song_metadata(&mut reader, &mut song).unwrap();
+        assert!(song.title.is_empty());
+        assert_eq!(22, reader.position());
+
+        let storage = crate::storage::MemoryStorage::new();
+        let path = Path::new("song.mp3");
+        storage
+            .put(path, data, crate::types::WriteMode::CreateNew)
+            .unwrap();
+        song.title = String::from("New title");
+        write_song_metadata(&storage, path, &song).unwrap();
+
+        let written = storage.get(path).unwrap();
+        assert!(written.ends_with(b"audio"));
+        let mut read = crate::song::Song::default();
+        read_song_metadata(&mut std::io::Cursor::new(written), &mut read).unwrap();
+        assert_eq!(song.title, read.title);
+    }
+}
@@ -0,0 +1,271 @@
+//! Parsing of the headers of PNG, JPEG, WebP and GIF images
+
+use crate::constants::{magic, mime};
+
+/// Length of the PNG signature and the IHDR chunk up to the image's height
+const PNG_IHDR_END: usize = 24;
+/// Length of the WebP RIFF header and the first chunk's header
+const WEBP_CHUNK_START: usize = 20;
+/// Length of the GIF signature and logical screen descriptor
+const GIF_HEADER_END: usize = 13;
+/// Starts a GIF image descriptor, one for each frame
+const GIF_IMAGE_DESCRIPTOR: u8 = 0x2C;
+/// Starts a GIF extension block
+const GIF_EXTENSION: u8 = 0x21;
+/// Labels a GIF application extension
+const GIF_APPLICATION_EXTENSION: u8 = 0xFF;
+/// Ends a GIF
+const GIF_TRAILER: u8 = 0x3B;
+/// Application extension that loops the frames of an animated GIF, with its length
+const GIF_NETSCAPE_LOOP: &[u8] = b"\x0BNETSCAPE2.0";
+
+/// Properties of an image read from its headers
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ImageInfo {
+    pub mime_type: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Reads the MIME type and dimensions of a PNG, JPEG, WebP or GIF image
+pub fn inspect(data: &[u8]) -> Result<ImageInfo, crate::Error> {
+    if data.starts_with(magic::PNG) {
+        png(data)
+    } else if data.starts_with(magic::JPEG) {
+        jpeg(data)
+    } else if data.starts_with(magic::RIFF) && data.get(8..12) == Some(magic::WEBP) {
+        webp(data)
+    } else if data.starts_with(magic::GIF87A) || data.starts_with(magic::GIF89A) {
+        gif(data)
+    } else {
+        Err(crate::Error::InvalidImage)
+    }
+}
+
+/// The IHDR chunk always comes first and holds the dimensions
+fn png(data: &[u8]) -> Result<ImageInfo, crate::Error> {
+    if data.len() < PNG_IHDR_END || &data[12..16] != b"IHDR" {
+        return Err(crate::Error::InvalidImage);
+    }
+
+    Ok(ImageInfo {
+        mime_type: String::from(mime::PNG),
+        width: read_u32(&data[16..20]),
+        height: read_u32(&data[20..24]),
+    })
+}
+
+/// Walks the JPEG segments up to the start of frame, which holds the dimensions
+fn jpeg(data: &[u8]) -> Result<ImageInfo, crate::Error> {
+    let mut offset = 2;
+
+    loop {
+        // Markers can be padded with any number of 0xFF fill bytes
+        while data.get(offset) == Some(&0xFF) && data.get(offset + 1) == Some(&0xFF) {
+            offset += 1;
+        }
+
+        let marker = match data.get(offset..offset + 2) {
+            Some([0xFF, marker]) => *marker,
+            _ => return Err(crate::Error::InvalidImage),
+        };
+        offset += 2;
+
+        match marker {
+            // Standalone markers have no length
+            0x01 | 0xD0..=0xD7 => continue,
+            // Start of scan or end of image before any frame
+            0xDA | 0xD9 => return Err(crate::Error::InvalidImage),
+            _ => {}
+        }
+
+        let length = match data.get(offset..offset + 2) {
+            Some(bytes) => u16::from_be_bytes([bytes[0], bytes[1]]) as usize,
+            None => return Err(crate::Error::InvalidImage),
+        };
+        if length < 2 {
+            return Err(crate::Error::InvalidImage);
+        }
+
+        // SOF0 to SOF15, except DHT, JPG and DAC which share the range
+        if (0xC0..=0xCF).contains(&marker) && !matches!(marker, 0xC4 | 0xC8 | 0xCC) {
+            return match data.get(offset + 3..offset + 7) {
+                Some(bytes) => Ok(ImageInfo {
+                    mime_type: String::from(mime::JPEG),
+                    width: u16::from_be_bytes([bytes[2], bytes[3]]) as u32,
+                    height: u16::from_be_bytes([bytes[0], bytes[1]]) as u32,
+                }),
+                None => Err(crate::Error::InvalidImage),
+            };
+        }
+
+        offset += length;
+    }
+}
+
+/// The first chunk is a lossy, lossless or extended header, each storing dimensions differently
+fn webp(data: &[u8]) -> Result<ImageInfo, crate::Error> {
+    let chunk = match data.get(WEBP_CHUNK_START..) {
+        Some(chunk) => chunk,
+        None => return Err(crate::Error::InvalidImage),
+    };
+
+    let (width, height) = match (&data[12..16], chunk) {
+        // Frame tag, then a start code and 14 bit dimensions
+        (b"VP8 ", [_, _, _, 0x9D, 0x01, 0x2A, w0, w1, h0, h1, ..]) => (
+            (u16::from_le_bytes([*w0, *w1]) & 0x3FFF) as u32,
+            (u16::from_le_bytes([*h0, *h1]) & 0x3FFF) as u32,
+        ),
+        // Signature, then 14 bit dimensions less one packed together
+        (b"VP8L", [0x2F, b0, b1, b2, b3, ..]) => {
+            let bits = u32::from_le_bytes([*b0, *b1, *b2, *b3]);
+            ((bits & 0x3FFF) + 1, ((bits >> 14) & 0x3FFF) + 1)
+        }
+        // Flags, then 24 bit canvas dimensions less one
+        (b"VP8X", [_, _, _, _, w0, w1, w2, h0, h1, h2, ..]) => (
+            u32::from_le_bytes([*w0, *w1, *w2, 0]) + 1,
+            u32::from_le_bytes([*h0, *h1, *h2, 0]) + 1,
+        ),
+        _ => return Err(crate::Error::InvalidImage),
+    };
+
+    Ok(ImageInfo {
+        mime_type: String::from(mime::WEBP),
+        width,
+        height,
+    })
+}
+
+/// The logical screen follows the signature. The blocks are walked to reject animated GIFs, which
+/// have several image descriptors or a looping extension
+fn gif(data: &[u8]) -> Result<ImageInfo, crate::Error> {
+    if data.len() < GIF_HEADER_END {
+        return Err(crate::Error::InvalidImage);
+    }
+
+    let mut offset = GIF_HEADER_END + gif_color_table_length(data[10]);
+    let mut frames = 0;
+    loop {
+        match data.get(offset) {
+            Some(&GIF_IMAGE_DESCRIPTOR) => {
+                frames += 1;
+                if frames > 1 {
+                    return Err(crate::Error::InvalidImage);
+                }
+
+                // Position, dimensions and flags, then the local color table and LZW code size
+                let flags = match data.get(offset + 9) {
+                    Some(flags) => *flags,
+                    None => return Err(crate::Error::InvalidImage),
+                };
+                offset = skip_gif_sub_blocks(data, offset + 11 + gif_color_table_length(flags))?;
+            }
+            Some(&GIF_EXTENSION) => {
+                if data.get(offset + 1) == Some(&GIF_APPLICATION_EXTENSION)
+                    && data.get(offset + 2..offset + 2 + GIF_NETSCAPE_LOOP.len())
+                        == Some(GIF_NETSCAPE_LOOP)
+                {
+                    return Err(crate::Error::InvalidImage);
+                }
+                offset = skip_gif_sub_blocks(data, offset + 2)?;
+            }
+            Some(&GIF_TRAILER) if frames == 1 => break,
+            _ => return Err(crate::Error::InvalidImage),
+        }
+    }
+
+    Ok(ImageInfo {
+        mime_type: String::from(mime::GIF),
+        width: u16::from_le_bytes([data[6], data[7]]) as u32,
+        height: u16::from_le_bytes([data[8], data[9]]) as u32,
+    })
+}
+
+/// Gets the length of the color table that follows a GIF descriptor with the given flags
+fn gif_color_table_length(flags: u8) -> usize {
+    if flags & 0x80 == 0 {
+        0
+    } else {
+        3 << ((flags & 0x07) + 1)
+    }
+}
+
+/// Skips GIF data sub-blocks, each prefixed with its length and ended by an empty one. Returns
+/// the offset after them
+fn skip_gif_sub_blocks(data: &[u8], mut offset: usize) -> Result<usize, crate::Error> {
+    loop {
+        match data.get(offset) {
+            Some(0) => return Ok(offset + 1),
+            Some(length) => offset += 1 + *length as usize,
+            None => return Err(crate::Error::InvalidImage),
+        }
+    }
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]])
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_inspect_jpeg() {
+        let data: Vec<u8> = vec![
+            0xFF, 0xD8, // SOI
+            0xFF, 0xE0, 0x00, 0x04, 0x00, 0x00, // APP0
+            0xFF, 0xFF, 0xC2, 0x00, 0x0B, 0x08, 0x01, 0xE0, 0x02, 0x80, 0x01, 0x01, 0x11,
+            0x00, // SOF2 with a fill byte, 640x480
+        ];
+
+        let info = super::inspect(&data).unwrap();
+        assert_eq!(crate::constants::mime::JPEG, info.mime_type);
+        assert_eq!((640, 480), (info.width, info.height));
+
+        assert!(super::inspect(&data[..12]).is_err());
+    }
+
+    #[test]
+    fn test_inspect_webp() {
+        let header = |fourcc: &[u8], chunk: &[u8]| -> Vec<u8> {
+            [b"RIFF\0\0\0\0WEBP", fourcc, b"\0\0\0\0", chunk].concat()
+        };
+
+        let lossy = header(
+            b"VP8 ",
+            &[0, 0, 0, 0x9D, 0x01, 0x2A, 0x80, 0x02, 0xE0, 0x01],
+        );
+        let lossless = header(b"VP8L", &[0x2F, 0x7F, 0xC2, 0x77, 0x00]);
+        let extended = header(b"VP8X", &[0, 0, 0, 0, 0x7F, 0x02, 0x00, 0xDF, 0x01, 0x00]);
+
+        for data in [lossy, lossless, extended] {
+            let info = super::inspect(&data).unwrap();
+            assert_eq!(crate::constants::mime::WEBP, info.mime_type);
+            assert_eq!((640, 480), (info.width, info.height));
+        }
+    }
+
+    #[test]
+    fn test_inspect_gif() {
+        let header: &[u8] = b"GIF89a\x02\x00\x01\x00\x80\x00\x00\0\0\0\xFF\xFF\xFF";
+        let frame: &[u8] = &[
+            0x2C, 0, 0, 0, 0, 2, 0, 1, 0, 0x00, // Image descriptor
+            0x02, 0x02, 0x44, 0x01, 0x00, // LZW code size and image data
+        ];
+        let netscape: &[u8] = b"\x21\xFF\x0BNETSCAPE2.0\x03\x01\x00\x00\x00";
+        let comment: &[u8] = b"\x21\xFE\x02hi\x00";
+
+        let still = [header, comment, frame, b";"].concat();
+        let info = super::inspect(&still).unwrap();
+        assert_eq!(crate::constants::mime::GIF, info.mime_type);
+        assert_eq!((2, 1), (info.width, info.height));
+
+        let frames = [header, frame, frame, b";"].concat();
+        let looping = [header, netscape, frame, b";"].concat();
+        for data in [frames, looping, [header, frame].concat()] {
+            assert!(matches!(
+                super::inspect(&data),
+                Err(crate::Error::InvalidImage)
+            ));
+        }
+    }
+}
@@ -0,0 +1,211 @@
+//! Parsing and writing of the metadata embedded in audio files, and parsing of image headers
+
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+pub mod flac;
+pub mod id3;
+pub mod image;
+pub mod mpeg;
+pub mod wav;
+
+/// Number of bytes needed to tell the supported audio formats apart
+const FORMAT_HEADER_LENGTH: u64 = 12;
+
+/// Fills the song's fields from the metadata of a FLAC, MP3 or WAV stream. The format is
+/// determined from the start of the stream
+pub fn read_song_metadata<R: Read + Seek>(
+    reader: &mut R,
+    song: &mut crate::song::Song,
+) -> Result<(), crate::Error> {
+    let header = read_format_header(reader)?;
+
+    match crate::song::detect_music_type(&header) {
+        crate::types::MusicType::FlacExtension => flac::read_song_metadata(reader, song),
+        crate::types::MusicType::WavExtension => wav::read_song_metadata(reader, song),
+        crate::types::MusicType::MPThreeExtension => {
+            id3::read_song_metadata(reader, song)?;
+            // The frames are more reliable than the TLEN frame of the tag
+            if let Ok(duration) = mpeg::duration(reader) {
+                song.duration = duration.round() as i32;
+            }
+            Ok(())
+        }
+        _ => Err(crate::Error::UnsupportedAudioFormat),
+    }
+}
+
+/// Computes the duration in seconds of a FLAC, MP3 or WAV stream from its audio headers
+pub fn duration<R: Read + Seek>(reader: &mut R) -> Result<f64, crate::Error> {
+    let header = read_format_header(reader)?;
+
+    match crate::song::detect_music_type(&header) {
+        crate::types::MusicType::FlacExtension => flac::duration(reader),
+        crate::types::MusicType::WavExtension => wav::duration(reader),
+        crate::types::MusicType::MPThreeExtension => mpeg::duration(reader),
+        _ => Err(crate::Error::UnsupportedAudioFormat),
+    }
+}
+
+/// Gets the technical properties of a FLAC, MP3 or WAV stream from its audio headers
+pub fn audio_properties<R: Read + Seek>(
+    reader: &mut R,
+) -> Result<crate::audio_properties::AudioProperties, crate::Error> {
+    let header = read_format_header(reader)?;
+
+    match crate::song::detect_music_type(&header) {
+        crate::types::MusicType::FlacExtension => flac::audio_properties(reader),
+        crate::types::MusicType::WavExtension => wav::audio_properties(reader),
+        crate::types::MusicType::MPThreeExtension => mpeg::audio_properties(reader),
+        _ => Err(crate::Error::UnsupportedAudioFormat),
+    }
+}
+
+/// Writes the song's fields into the metadata of a FLAC, MP3 or WAV file stored under `key`
+pub fn write_song_metadata<S: crate::storage::Storage>(
+    storage: &S,
+    key: &Path,
+    song: &crate::song::Song,
+) -> Result<(), crate::Error> {
+    let header = read_format_header(&mut storage.open(key)?)?;
+
+    match crate::song::detect_music_type(&header) {
+        crate::types::MusicType::FlacExtension => flac::write_song_metadata(storage, key, song),
+        crate::types::MusicType::WavExtension => wav::write_song_metadata(storage, key, song),
+        crate::types::MusicType::MPThreeExtension => id3::write_song_metadata(storage, key, song),
+        _ => Err(crate::Error::UnsupportedAudioFormat),
+    }
+}
+
+/// Replaces the first `original_length` bytes of the file stored under `key`, the metadata
+/// preceding the audio, with `header`. A header of the same length is overwritten in place.
+/// Otherwise the file is rewritten with the new header and a streamed copy of the audio, so a
+/// failure never leaves a truncated file behind
+fn replace_header<S: crate::storage::Storage>(
+    storage: &S,
+    key: &Path,
+    header: &[u8],
+    original_length: u64,
+) -> Result<(), crate::Error> {
+    if header.len() as u64 == original_length {
+        return storage.write_at(key, 0, header);
+    }
+
+    storage.rewrite(key, |source, writer| {
+        source.seek(SeekFrom::Start(original_length))?;
+        writer.write_all(header)?;
+        std::io::copy(source, writer)?;
+        Ok(())
+    })
+}
+
+/// Reads the first bytes of a stream and rewinds it
+fn read_format_header<R: Read + Seek>(reader: &mut R) -> Result<Vec<u8>, crate::Error> {
+    reader.seek(SeekFrom::Start(0))?;
+    let mut header: Vec<u8> = Vec::new();
+    reader.take(FORMAT_HEADER_LENGTH).read_to_end(&mut header)?;
+    reader.seek(SeekFrom::Start(0))?;
+
+    header.resize(FORMAT_HEADER_LENGTH as usize, 0);
+    Ok(header)
+}
+
+/// Fills the buffer from the reader. Running out of data means the media is truncated, which is
+/// reported as `truncated` rather than as an I/O error
+fn read_exact<R: Read>(
+    reader: &mut R,
+    buffer: &mut [u8],
+    truncated: crate::Error,
+) -> Result<(), crate::Error> {
+    match reader.read_exact(buffer) {
+        Ok(()) => Ok(()),
+        Err(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => Err(truncated),
+        Err(err) => Err(crate::Error::Io(err)),
+    }
+}
+
+/// Parses a number from a tag value. Values such as "3/12" yield the number before the slash
+pub fn parse_number(value: &str) -> i32 {
+    let number = match value.split_once('/') {
+        Some((number, _)) => number,
+        None => value,
+    };
+
+    number.trim().parse::<i32>().unwrap_or(0)
+}
+
+/// Parses the total from a tag value such as "3/12"
+pub fn parse_total(value: &str) -> i32 {
+    match value.split_once('/') {
+        Some((_, total)) => total.trim().parse::<i32>().unwrap_or(0),
+        None => 0,
+    }
+}
+
+/// Parses the year from a date tag value. Values such as "2025-03-22" yield 2025
+pub fn parse_year(value: &str) -> i32 {
+    let digits: String = value
+        .trim()
+        .chars()
+        .take_while(|c| c.is_ascii_digit())
+        .collect();
+
+    if digits.len() >= 4 {
+        digits[..4].parse::<i32>().unwrap_or(0)
+    } else {
+        0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_parse_number_and_total() {
+        assert_eq!(3, super::parse_number("3/12"));
+        assert_eq!(12, super::parse_total("3/12"));
+        assert_eq!(7, super::parse_number(" 7 "));
+        assert_eq!(0, super::parse_total("7"));
+        assert_eq!(0, super::parse_number("seven"));
+    }
+
+    #[test]
+    fn test_parse_year() {
+        assert_eq!(2025, super::parse_year("2025-03-22"));
+        assert_eq!(1999, super::parse_year("1999"));
+        assert_eq!(0, super::parse_year("99"));
+    }
+
+    #[test]
+    fn test_malformed_media_errors() {
+        let mut song = crate::song::Song::default();
+        let mut garbage = std::io::Cursor::new(b"not audio at all".to_vec());
+        assert!(matches!(
+            super::read_song_metadata(&mut garbage, &mut song),
+            Err(crate::Error::UnsupportedAudioFormat)
+        ));
+
+        let mut truncated = std::io::Cursor::new(b"fLaC\x00\x00\x00\x22\x00".to_vec());
+        assert!(matches!(
+            super::duration(&mut truncated),
+            Err(crate::Error::InvalidFlacMetadata)
+        ));
+    }
+
+    #[test]
+    fn test_replace_header() {
+        let storage = crate::storage::LocalStorage;
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("song.flac");
+        std::fs::write(&path, b"oldheader-audio").unwrap();
+
+        super::replace_header(&storage, &path, b"newheader", 9).unwrap();
+        assert_eq!(b"newheader-audio", &std::fs::read(&path).unwrap()[..]);
+
+        super::replace_header(&storage, &path, b"much longer header", 9).unwrap();
+        assert_eq!(
+            b"much longer header-audio",
+            &std::fs::read(&path).unwrap()[..]
+        );
+        assert_eq!(1, std::fs::read_dir(temp_dir.path()).unwrap().count());
+    }
+}
@@ -0,0 +1,343 @@
+//! MPEG audio frame headers. Covers MPEG-1, MPEG-2 and MPEG-2.5, layers I to III
+
+use std::io::{Read, Seek, SeekFrom};
+
+/// Size of a frame header
+pub const HEADER_LENGTH: usize = 4;
+
+/// Markers of the VBR headers stored in the first frame of a stream
+const XING_MARKER: &[u8; 4] = b"Xing";
+const INFO_MARKER: &[u8; 4] = b"Info";
+const VBRI_MARKER: &[u8; 4] = b"VBRI";
+/// The VBRI header is always located 32 bytes after the frame header
+const VBRI_OFFSET: usize = HEADER_LENGTH + 32;
+/// Flag of a Xing header signaling that the frame count is present
+const XING_FRAMES_FLAG: u32 = 0x01;
+/// How far into the stream to look for the first frame
+const SYNC_SEARCH_LENGTH: u64 = 64 * 1024;
+/// Size of the reads done while walking the frames of a stream
+const READ_LENGTH: u64 = 64 * 1024;
+
+/// Bitrates in kbps, indexed by the bitrate index of the header
+const BITRATES_V1_L1: [u32; 15] = [
+    0, 32, 64, 96, 128, 160, 192, 224, 256, 288, 320, 352, 384, 416, 448,
+];
+const BITRATES_V1_L2: [u32; 15] = [
+    0, 32, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320, 384,
+];
+const BITRATES_V1_L3: [u32; 15] = [
+    0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320,
+];
+const BITRATES_V2_L1: [u32; 15] = [
+    0, 32, 48, 56, 64, 80, 96, 112, 128, 144, 160, 176, 192, 224, 256,
+];
+const BITRATES_V2_L2_L3: [u32; 15] = [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Version {
+    Mpeg1,
+    Mpeg2,
+    Mpeg25,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Layer {
+    One,
+    Two,
+    Three,
+}
+
+#[derive(Clone, Debug)]
+pub struct FrameHeader {
+    pub version: Version,
+    pub layer: Layer,
+    /// In kbps. 0 for free format streams
+    pub bitrate: u32,
+    pub sample_rate: u32,
+    pub padding: bool,
+    pub channels: u8,
+}
+
+impl FrameHeader {
+    /// Parses a frame header. Returns None when the bytes are not a valid header
+    pub fn parse(data: &[u8]) -> Option<FrameHeader> {
+        if data.len() < HEADER_LENGTH || data[0] != 0xFF || data[1] & 0xE0 != 0xE0 {
+            return None;
+        }
+
+        let version = match (data[1] >> 3) & 0x03 {
+            0 => Version::Mpeg25,
+            2 => Version::Mpeg2,
+            3 => Version::Mpeg1,
+            _ => return None,
+        };
+        let layer = match (data[1] >> 1) & 0x03 {
+            1 => Layer::Three,
+            2 => Layer::Two,
+            3 => Layer::One,
+            _ => return None,
+        };
+
+        let bitrate_index = usize::from(data[2] >> 4);
+        let sample_rate_index = usize::from((data[2] >> 2) & 0x03);
+        if bitrate_index == 0x0F || sample_rate_index == 0x03 {
+            return None;
+        }
+
+        let bitrates = match (version, layer) {
+            (Version::Mpeg1, Layer::One) => &BITRATES_V1_L1,
+            (Version::Mpeg1, Layer::Two) => &BITRATES_V1_L2,
+            (Version::Mpeg1, Layer::Three) => &BITRATES_V1_L3,
+            (_, Layer::One) => &BITRATES_V2_L1,
+            (_, _) => &BITRATES_V2_L2_L3,
+        };
+        let sample_rates: [u32; 3] = match version {
+            Version::Mpeg1 => [44100, 48000, 32000],
+            Version::Mpeg2 => [22050, 24000, 16000],
+            Version::Mpeg25 => [11025, 12000, 8000],
+        };
+
+        Some(FrameHeader {
+            version,
+            layer,
+            bitrate: bitrates[bitrate_index],
+            sample_rate: sample_rates[sample_rate_index],
+            padding: (data[2] >> 1) & 0x01 == 1,
+            channels: if data[3] >> 6 == 0x03 { 1 } else { 2 },
+        })
+    }
+
+    /// Number of samples per channel in the frame
+    pub fn samples(&self) -> u32 {
+        match (self.layer, self.version) {
+            (Layer::One, _) => 384,
+            (Layer::Two, _) => 1152,
+            (Layer::Three, Version::Mpeg1) => 1152,
+            (Layer::Three, _) => 576,
+        }
+    }
+
+    /// Size of the frame in bytes including the header. 0 for free format streams
+    pub fn frame_length(&self) -> usize {
+        let bitrate = self.bitrate as usize * 1000;
+        let sample_rate = self.sample_rate as usize;
+        let padding = usize::from(self.padding);
+
+        match self.layer {
+            Layer::One => (12 * bitrate / sample_rate + padding) * 4,
+            _ => (self.samples() as usize / 8) * bitrate / sample_rate + padding,
+        }
+    }
+
+    /// Size of the side information following the header of a Layer III frame
+    fn side_information_length(&self) -> usize {
+        match (self.version, self.channels) {
+            (Version::Mpeg1, 1) => 17,
+            (Version::Mpeg1, _) => 32,
+            (_, 1) => 9,
+            (_, _) => 17,
+        }
+    }
+
+    /// Duration of the frame in seconds
+    pub fn duration(&self) -> f64 {
+        f64::from(self.samples()) / f64::from(self.sample_rate)
+    }
+}
+
+/// Gets the number of audio frames declared by the Xing, Info or VBRI header of the first frame
+pub fn vbr_frame_count(header: &FrameHeader, frame: &[u8]) -> Option<u32> {
+    let read_u32 = |offset: usize| {
+        frame
+            .get(offset..offset + 4)
+            .map(|bytes| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    };
+
+    let xing_offset = HEADER_LENGTH + header.side_information_length();
+    if let Some(marker) = frame.get(xing_offset..xing_offset + 4)
+        && (marker == XING_MARKER || marker == INFO_MARKER)
+    {
+        let flags = read_u32(xing_offset + 4)?;
+        return if flags & XING_FRAMES_FLAG != 0 {
+            read_u32(xing_offset + 8)
+        } else {
+            None
+        };
+    }
+
+    if frame.get(VBRI_OFFSET..VBRI_OFFSET + 4) == Some(VBRI_MARKER) {
+        // Marker, version, delay, quality and byte count precede the frame count
+        return read_u32(VBRI_OFFSET + 14);
+    }
+
+    None
+}
+
+/// Locates the first frame of a stream, skipping a leading ID3 tag. Returns the offset of the
+/// frame, its header and the bytes read starting at the frame. The reader is left after those
+/// bytes
+fn first_frame<R: Read + Seek>(
+    reader: &mut R,
+) -> Result<(u64, FrameHeader, Vec<u8>), crate::Error> {
+    reader.seek(SeekFrom::Start(0))?;
+    let mut header = [0u8; super::id3::HEADER_LENGTH];
+    let start = match reader.read_exact(&mut header) {
+        Ok(_) => super::id3::tag_length(&header).unwrap_or(0),
+        Err(_) => 0,
+    };
+    reader.seek(SeekFrom::Start(start as u64))?;
+
+    let mut buffer: Vec<u8> = Vec::new();
+    reader.take(SYNC_SEARCH_LENGTH).read_to_end(&mut buffer)?;
+    let first = (0..buffer.len()).find(|&index| starts_with_frame(&buffer[index..]));
+
+    match first.and_then(|first| FrameHeader::parse(&buffer[first..]).map(|h| (first, h))) {
+        Some((first, header)) => {
+            buffer.drain(..first);
+            Ok(((start + first) as u64, header, buffer))
+        }
+        None => Err(crate::Error::InvalidMpegStream),
+    }
+}
+
+/// Computes the duration in seconds of an MPEG audio stream. The frame count of a Xing or VBRI
+/// header is used when present, otherwise every frame is walked
+pub fn duration<R: Read + Seek>(reader: &mut R) -> Result<f64, crate::Error> {
+    let (_, first_header, mut buffer) = first_frame(reader)?;
+    if let Some(frames) = vbr_frame_count(&first_header, &buffer) {
+        return Ok(f64::from(frames) * first_header.duration());
+    }
+
+    // Walk every frame, reading the stream in chunks
+    let mut cursor: usize = 0;
+    let mut total: f64 = 0.0;
+    let mut end_of_stream = false;
+    loop {
+        if buffer.len().saturating_sub(cursor) < HEADER_LENGTH {
+            if end_of_stream {
+                break;
+            }
+            if cursor > buffer.len() {
+                let skip = (cursor - buffer.len()) as u64;
+                std::io::copy(&mut reader.by_ref().take(skip), &mut std::io::sink())?;
+                buffer.clear();
+            } else {
+                buffer.drain(..cursor);
+            }
+            cursor = 0;
+            end_of_stream = reader.by_ref().take(READ_LENGTH).read_to_end(&mut buffer)? == 0;
+            continue;
+        }
+
+        match FrameHeader::parse(&buffer[cursor..]) {
+            Some(header)
+                if header.sample_rate == first_header.sample_rate && header.frame_length() > 0 =>
+            {
+                total += header.duration();
+                cursor += header.frame_length();
+            }
+            _ => break,
+        }
+    }
+
+    Ok(total)
+}
+
+/// Gets the technical properties of an MPEG audio stream. The bitrate is averaged over the
+/// whole stream so that VBR streams are reported accurately
+pub fn audio_properties<R: Read + Seek>(
+    reader: &mut R,
+) -> Result<crate::audio_properties::AudioProperties, crate::Error> {
+    let (offset, header, _) = first_frame(reader)?;
+    let duration = duration(reader)?;
+    let length = reader.seek(SeekFrom::End(0))?.saturating_sub(offset);
+
+    let bitrate = if duration > 0.0 {
+        (length as f64 * 8.0 / duration / 1000.0).round() as i32
+    } else {
+        header.bitrate as i32
+    };
+    let codec = match header.layer {
+        Layer::One => crate::audio_properties::codec::MP1,
+        Layer::Two => crate::audio_properties::codec::MP2,
+        Layer::Three => crate::audio_properties::codec::MP3,
+    };
+
+    Ok(crate::audio_properties::AudioProperties {
+        codec: String::from(codec),
+        sample_rate: header.sample_rate as i32,
+        bit_depth: 0,
+        channels: i32::from(header.channels),
+        bitrate,
+    })
+}
+
+/// Whether the data starts with an MPEG audio frame. When the data is long enough, the frame
+/// that follows must be valid as well
+pub fn starts_with_frame(data: &[u8]) -> bool {
+    match FrameHeader::parse(data) {
+        Some(header) => {
+            let length = header.frame_length();
+            if length == 0 || data.len() < length + HEADER_LENGTH {
+                true
+            } else {
+                FrameHeader::parse(&data[length..]).is_some()
+            }
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_frame_header() {
+        let header = FrameHeader::parse(&[0xFF, 0xFB, 0x90, 0x00]).unwrap();
+        assert_eq!(Version::Mpeg1, header.version);
+        assert_eq!(Layer::Three, header.layer);
+        assert_eq!(128, header.bitrate);
+        assert_eq!(44100, header.sample_rate);
+        assert_eq!(2, header.channels);
+        assert_eq!(417, header.frame_length());
+
+        assert!(FrameHeader::parse(&[0xFF, 0xFB, 0xF0, 0x00]).is_none());
+        assert!(FrameHeader::parse(&[0xFF, 0xE9, 0x90, 0x00]).is_none());
+    }
+
+    fn frame(marker: &[u8; 4], offset: usize, frames: u32) -> Vec<u8> {
+        let mut frame = vec![0u8; 417];
+        frame[..4].copy_from_slice(&[0xFF, 0xFB, 0x90, 0x00]);
+        frame[offset..offset + 4].copy_from_slice(marker);
+        if marker == VBRI_MARKER {
+            frame[offset + 14..offset + 18].copy_from_slice(&frames.to_be_bytes());
+        } else {
+            frame[offset + 4..offset + 8].copy_from_slice(&XING_FRAMES_FLAG.to_be_bytes());
+            frame[offset + 8..offset + 12].copy_from_slice(&frames.to_be_bytes());
+        }
+        frame
+    }
+
+    #[test]
+    fn test_vbr_duration() {
+        for (marker, offset) in [(XING_MARKER, 36), (INFO_MARKER, 36), (VBRI_MARKER, 36)] {
+            let mut data = frame(marker, offset, 1000);
+            data.extend_from_slice(&frame(b"\0\0\0\0", 100, 0));
+
+            let duration = duration(&mut std::io::Cursor::new(data)).unwrap();
+            assert!((duration - 1000.0 * 1152.0 / 44100.0).abs() < 0.001);
+        }
+    }
+
+    #[test]
+    fn test_cbr_duration() {
+        let mut data = b"junk".to_vec();
+        for _ in 0..100 {
+            data.extend_from_slice(&frame(b"\0\0\0\0", 100, 0));
+        }
+
+        let duration = duration(&mut std::io::Cursor::new(data)).unwrap();
+        assert!((duration - 100.0 * 1152.0 / 44100.0).abs() < 0.001);
+    }
+}
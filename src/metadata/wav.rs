@@ -0,0 +1,496 @@
+//! RIFF/WAVE files. Metadata is read from the LIST/INFO chunk and from an embedded id3 chunk
+
+use std::io::{Read, Seek, SeekFrom};
+use std::path::Path;
+
+pub const RIFF_MARKER: &[u8; 4] = b"RIFF";
+pub const WAVE_MARKER: &[u8; 4] = b"WAVE";
+
+/// Identifiers of the chunks of a WAVE file
+pub mod chunk_id {
+    pub const FORMAT: &[u8; 4] = b"fmt ";
+    pub const DATA: &[u8; 4] = b"data";
+    pub const LIST: &[u8; 4] = b"LIST";
+    pub const ID3: &[u8; 4] = b"id3 ";
+    pub const ID3_UPPERCASE: &[u8; 4] = b"ID3 ";
+}
+
+/// Identifiers of the INFO list entries used to fill a Song
+pub mod info_id {
+    pub const INFO: &[u8; 4] = b"INFO";
+    pub const TITLE: &[u8; 4] = b"INAM";
+    pub const ARTIST: &[u8; 4] = b"IART";
+    pub const ALBUM: &[u8; 4] = b"IPRD";
+    pub const GENRE: &[u8; 4] = b"IGNR";
+    pub const DATE: &[u8; 4] = b"ICRD";
+    pub const TRACK: &[u8; 4] = b"ITRK";
+    pub const TRACK_ALT: &[u8; 4] = b"IPRT";
+}
+
+/// Values of the audio format field of the fmt chunk
+pub mod audio_format {
+    pub const PCM: u16 = 0x0001;
+    pub const IEEE_FLOAT: u16 = 0x0003;
+    pub const EXTENSIBLE: u16 = 0xFFFE;
+}
+
+/// Size of the RIFF header, including the WAVE form type
+const RIFF_HEADER_LENGTH: usize = 12;
+/// Size of the header preceding the data of every chunk
+const CHUNK_HEADER_LENGTH: usize = 8;
+
+#[derive(Clone, Debug, Default)]
+pub struct Chunk {
+    pub id: [u8; 4],
+    pub data: Vec<u8>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Format {
+    pub audio_format: u16,
+    pub channels: u16,
+    pub sample_rate: u32,
+    pub byte_rate: u32,
+    pub block_align: u16,
+    pub bits_per_sample: u16,
+}
+
+/// Chunk as found while walking a WAVE stream. The audio of the data chunk is left in the stream
+enum Slot {
+    Loaded(Chunk),
+    /// The data chunk, with the offset of its audio in the stream and its declared length
+    Data {
+        offset: u64,
+        length: u64,
+    },
+}
+
+/// Entries of a LIST/INFO chunk
+#[derive(Clone, Debug, Default)]
+pub struct Info {
+    pub entries: Vec<([u8; 4], String)>,
+}
+
+impl Chunk {
+    /// Serializes the chunk including its header and pad byte
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = Vec::with_capacity(CHUNK_HEADER_LENGTH + self.data.len() + 1);
+        bytes.extend_from_slice(&self.id);
+        bytes.extend_from_slice(&(self.data.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&self.data);
+        if self.data.len() % 2 == 1 {
+            bytes.push(0);
+        }
+        bytes
+    }
+}
+
+impl Format {
+    pub fn parse(data: &[u8]) -> Result<Format, crate::Error> {
+        if data.len() < 16 {
+            return Err(crate::Error::InvalidWavFile);
+        }
+
+        Ok(Format {
+            audio_format: u16::from_le_bytes([data[0], data[1]]),
+            channels: u16::from_le_bytes([data[2], data[3]]),
+            sample_rate: u32::from_le_bytes([data[4], data[5], data[6], data[7]]),
+            byte_rate: u32::from_le_bytes([data[8], data[9], data[10], data[11]]),
+            block_align: u16::from_le_bytes([data[12], data[13]]),
+            bits_per_sample: u16::from_le_bytes([data[14], data[15]]),
+        })
+    }
+}
+
+impl Info {
+    /// Parses the data of a LIST chunk. Returns None when the list is not an INFO list
+    pub fn parse(data: &[u8]) -> Option<Info> {
+        if data.len() < 4 || &data[..4] != info_id::INFO {
+            return None;
+        }
+
+        let mut entries: Vec<([u8; 4], String)> = Vec::new();
+        let mut position: usize = 4;
+        while position + CHUNK_HEADER_LENGTH <= data.len() {
+            let mut id = [0u8; 4];
+            id.copy_from_slice(&data[position..position + 4]);
+            let length = u32::from_le_bytes([
+                data[position + 4],
+                data[position + 5],
+                data[position + 6],
+                data[position + 7],
+            ]) as usize;
+            position += CHUNK_HEADER_LENGTH;
+
+            let value = match data.get(position..position + length) {
+                Some(value) => value,
+                None => break,
+            };
+            let value = String::from_utf8_lossy(value);
+            entries.push((id, String::from(value.trim_end_matches('\0'))));
+            position += length + length % 2;
+        }
+
+        Some(Info { entries })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes: Vec<u8> = info_id::INFO.to_vec();
+        for (id, value) in &self.entries {
+            let mut data = value.as_bytes().to_vec();
+            data.push(0);
+            bytes.extend_from_slice(&Chunk { id: *id, data }.to_bytes());
+        }
+        bytes
+    }
+
+    pub fn get(&self, id: &[u8; 4]) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|(key, _)| key == id)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Replaces an entry. Empty values remove the entry
+    pub fn set(&mut self, id: &[u8; 4], value: &str) {
+        self.entries.retain(|(key, _)| key != id);
+        if !value.is_empty() {
+            self.entries.push((*id, String::from(value)));
+        }
+    }
+}
+
+/// Verifies the RIFF header of a stream, leaving the reader at the first chunk
+pub fn read_header<R: Read>(reader: &mut R) -> Result<(), crate::Error> {
+    let mut header = [0u8; RIFF_HEADER_LENGTH];
+    super::read_exact(reader, &mut header, crate::Error::InvalidWavFile)?;
+
+    if &header[..4] != RIFF_MARKER || &header[8..] != WAVE_MARKER {
+        Err(crate::Error::InvalidWavFile)
+    } else {
+        Ok(())
+    }
+}
+
+/// Reads every chunk of a WAVE stream. The data chunk is skipped, only its size is reported
+pub fn read_chunks<R: Read + Seek>(
+    reader: &mut R,
+) -> Result<(Vec<Chunk>, Option<u64>), crate::Error> {
+    let mut chunks: Vec<Chunk> = Vec::new();
+    let mut data_length: Option<u64> = None;
+    for slot in index_chunks(reader)? {
+        match slot {
+            Slot::Loaded(chunk) => chunks.push(chunk),
+            Slot::Data { length, .. } => data_length = Some(length),
+        }
+    }
+
+    Ok((chunks, data_length))
+}
+
+/// Walks the chunks of a WAVE stream in order, loading every chunk but the data chunk
+fn index_chunks<R: Read + Seek>(reader: &mut R) -> Result<Vec<Slot>, crate::Error> {
+    read_header(reader)?;
+
+    let mut slots: Vec<Slot> = Vec::new();
+    loop {
+        let mut header = [0u8; CHUNK_HEADER_LENGTH];
+        match reader.read_exact(&mut header) {
+            Ok(_) => {}
+            Err(err) if err.kind() == std::io::ErrorKind::UnexpectedEof => break,
+            Err(err) => return Err(crate::Error::Io(err)),
+        }
+
+        let mut id = [0u8; 4];
+        id.copy_from_slice(&header[..4]);
+        let length = u64::from(u32::from_le_bytes([
+            header[4], header[5], header[6], header[7],
+        ]));
+        let padded_length = length + length % 2;
+
+        if &id == chunk_id::DATA {
+            let offset = reader.stream_position()?;
+            slots.push(Slot::Data { offset, length });
+            reader.seek(SeekFrom::Current(padded_length as i64))?;
+        } else {
+            // The length is untrusted, so the data is only allocated as it is read
+            let mut data: Vec<u8> = Vec::new();
+            reader.by_ref().take(length).read_to_end(&mut data)?;
+            if (data.len() as u64) < length {
+                return Err(crate::Error::InvalidWavFile);
+            }
+            if length % 2 == 1 {
+                let mut pad = [0u8; 1];
+                let _ = reader.read(&mut pad)?;
+            }
+            slots.push(Slot::Loaded(Chunk { id, data }));
+        }
+    }
+
+    Ok(slots)
+}
+
+/// Computes the duration in seconds of a WAVE stream from the sizes of its fmt and data chunks
+pub fn duration<R: Read + Seek>(reader: &mut R) -> Result<f64, crate::Error> {
+    let (chunks, data_length) = read_chunks(reader)?;
+    stream_duration(&chunks, data_length)
+}
+
+/// Gets the technical properties of a WAVE stream from its fmt chunk
+pub fn audio_properties<R: Read + Seek>(
+    reader: &mut R,
+) -> Result<crate::audio_properties::AudioProperties, crate::Error> {
+    let (chunks, _) = read_chunks(reader)?;
+    let format = match find_chunk(&chunks, chunk_id::FORMAT) {
+        Some(chunk) => Format::parse(&chunk.data)?,
+        None => {
+            return Err(crate::Error::InvalidWavFile);
+        }
+    };
+
+    let codec = match format.audio_format {
+        audio_format::PCM | audio_format::EXTENSIBLE => crate::audio_properties::codec::PCM,
+        audio_format::IEEE_FLOAT => crate::audio_properties::codec::PCM_FLOAT,
+        _ => crate::audio_properties::codec::WAV,
+    };
+
+    Ok(crate::audio_properties::AudioProperties {
+        codec: String::from(codec),
+        sample_rate: format.sample_rate as i32,
+        bit_depth: i32::from(format.bits_per_sample),
+        channels: i32::from(format.channels),
+        bitrate: (f64::from(format.byte_rate) * 8.0 / 1000.0).round() as i32,
+    })
+}
+
+fn stream_duration(chunks: &[Chunk], data_length: Option<u64>) -> Result<f64, crate::Error> {
+    match (find_chunk(chunks, chunk_id::FORMAT), data_length) {
+        (Some(format), Some(data_length)) => {
+            let format = Format::parse(&format.data)?;
+            if format.byte_rate > 0 {
+                Ok(data_length as f64 / f64::from(format.byte_rate))
+            } else {
+                Ok(0.0)
+            }
+        }
+        _ => Err(crate::Error::InvalidWavFile),
+    }
+}
+
+/// Fills the song's fields from the chunks of a WAVE stream. Values of an id3 chunk take
+/// precedence over the INFO list
+pub fn read_song_metadata<R: Read + Seek>(
+    reader: &mut R,
+    song: &mut crate::song::Song,
+) -> Result<(), crate::Error> {
+    let (chunks, data_length) = read_chunks(reader)?;
+    song.duration = stream_duration(&chunks, data_length)?.round() as i32;
+
+    if let Some(info) = info_chunk(&chunks) {
+        apply_info(&info, song);
+    }
+
+    // An unreadable id3 chunk is ignored, the same as when the metadata is written
+    if let Some(chunk) = id3_chunk(&chunks)
+        && let Ok(tag) = super::id3::Tag::parse(&chunk.data)
+    {
+        super::id3::apply_tag(&tag, song);
+    }
+
+    Ok(())
+}
+
+/// Copies the entries of an INFO list over to a song. Entries missing from the list are left
+/// untouched
+pub fn apply_info(info: &Info, song: &mut crate::song::Song) {
+    if let Some(title) = info.get(info_id::TITLE) {
+        song.title = String::from(title);
+    }
+    if let Some(artist) = info.get(info_id::ARTIST) {
+        song.artist = String::from(artist);
+    }
+    if let Some(album) = info.get(info_id::ALBUM) {
+        song.album = String::from(album);
+    }
+    if let Some(genre) = info.get(info_id::GENRE) {
+        song.genre = String::from(genre);
+    }
+    if let Some(date) = info.get(info_id::DATE) {
+        song.year = super::parse_year(date);
+    }
+    if let Some(track) = info
+        .get(info_id::TRACK)
+        .or_else(|| info.get(info_id::TRACK_ALT))
+    {
+        song.track = super::parse_number(track);
+    }
+}
+
+/// Builds an INFO list from the song's fields. Entries of the existing list that the song does
+/// not manage are preserved
+pub fn info_from_song(existing: Option<Info>, song: &crate::song::Song) -> Info {
+    let mut info = existing.unwrap_or_default();
+
+    info.set(info_id::TITLE, &song.title);
+    info.set(info_id::ARTIST, &song.artist);
+    info.set(info_id::ALBUM, &song.album);
+    info.set(info_id::GENRE, &song.genre);
+    info.set(
+        info_id::DATE,
+        &if song.year > 0 {
+            song.year.to_string()
+        } else {
+            String::new()
+        },
+    );
+    info.entries.retain(|(key, _)| key != info_id::TRACK_ALT);
+    info.set(
+        info_id::TRACK,
+        &if song.track > 0 {
+            song.track.to_string()
+        } else {
+            String::new()
+        },
+    );
+
+    info
+}
+
+/// Rewrites the INFO list and the id3 chunk of a WAVE file from the song's fields. Every other
+/// chunk is preserved. The file is rewritten atomically with the audio streamed from the original
+pub fn write_song_metadata<S: crate::storage::Storage>(
+    storage: &S,
+    key: &Path,
+    song: &crate::song::Song,
+) -> Result<(), crate::Error> {
+    let mut reader = std::io::BufReader::new(storage.open(key)?);
+    let slots = index_chunks(&mut reader)?;
+    let file_length = reader.seek(SeekFrom::End(0))?;
+
+    let chunks: Vec<Chunk> = slots
+        .iter()
+        .filter_map(|slot| match slot {
+            Slot::Loaded(chunk) => Some(chunk.clone()),
+            Slot::Data { .. } => None,
+        })
+        .collect();
+    let info = info_from_song(info_chunk(&chunks), song);
+    let existing_tag = match id3_chunk(&chunks) {
+        Some(chunk) => super::id3::Tag::parse(&chunk.data).ok(),
+        None => None,
+    };
+    let tag = super::id3::tag_from_song(existing_tag, song);
+
+    let mut info_written = false;
+    let mut tag_written = false;
+    let mut output: Vec<Slot> = Vec::with_capacity(slots.len() + 2);
+    for slot in slots {
+        match slot {
+            Slot::Loaded(chunk)
+                if &chunk.id == chunk_id::LIST && Info::parse(&chunk.data).is_some() =>
+            {
+                if !info_written {
+                    output.push(Slot::Loaded(Chunk {
+                        id: *chunk_id::LIST,
+                        data: info.to_bytes(),
+                    }));
+                    info_written = true;
+                }
+            }
+            Slot::Loaded(chunk)
+                if &chunk.id == chunk_id::ID3 || &chunk.id == chunk_id::ID3_UPPERCASE =>
+            {
+                if !tag_written {
+                    output.push(Slot::Loaded(Chunk {
+                        id: chunk.id,
+                        data: tag.to_bytes(0)?,
+                    }));
+                    tag_written = true;
+                }
+            }
+            // Streams written before their length was known may declare more audio than exists
+            Slot::Data { offset, length } => output.push(Slot::Data {
+                offset,
+                length: length.min(file_length.saturating_sub(offset)),
+            }),
+            slot => output.push(slot),
+        }
+    }
+    if !info_written {
+        output.push(Slot::Loaded(Chunk {
+            id: *chunk_id::LIST,
+            data: info.to_bytes(),
+        }));
+    }
+    if !tag_written {
+        output.push(Slot::Loaded(Chunk {
+            id: *chunk_id::ID3,
+            data: tag.to_bytes(0)?,
+        }));
+    }
+
+    let body_length = output.iter().fold(WAVE_MARKER.len() as u64, |total, slot| {
+        let length = match slot {
+            Slot::Loaded(chunk) => chunk.data.len() as u64,
+            Slot::Data { length, .. } => *length,
+        };
+        total + CHUNK_HEADER_LENGTH as u64 + length + length % 2
+    });
+    let body_length = match u32::try_from(body_length) {
+        Ok(body_length) => body_length,
+        Err(_) => return Err(crate::Error::InvalidWavFile),
+    };
+
+    storage.rewrite(key, |source, writer| {
+        writer.write_all(RIFF_MARKER)?;
+        writer.write_all(&body_length.to_le_bytes())?;
+        writer.write_all(WAVE_MARKER)?;
+        for slot in &output {
+            match slot {
+                Slot::Loaded(chunk) => writer.write_all(&chunk.to_bytes())?,
+                Slot::Data { offset, length } => {
+                    writer.write_all(chunk_id::DATA)?;
+                    writer.write_all(&(*length as u32).to_le_bytes())?;
+                    source.seek(SeekFrom::Start(*offset))?;
+                    std::io::copy(&mut Read::by_ref(source).take(*length), writer)?;
+                    if length % 2 == 1 {
+                        writer.write_all(&[0])?;
+                    }
+                }
+            }
+        }
+        Ok(())
+    })
+}
+
+pub fn find_chunk<'a>(chunks: &'a [Chunk], id: &[u8; 4]) -> Option<&'a Chunk> {
+    chunks.iter().find(|chunk| &chunk.id == id)
+}
+
+fn info_chunk(chunks: &[Chunk]) -> Option<Info> {
+    chunks
+        .iter()
+        .filter(|chunk| &chunk.id == chunk_id::LIST)
+        .find_map(|chunk| Info::parse(&chunk.data))
+}
+
+fn id3_chunk(chunks: &[Chunk]) -> Option<&Chunk> {
+    find_chunk(chunks, chunk_id::ID3).or_else(|| find_chunk(chunks, chunk_id::ID3_UPPERCASE))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_info_round_trip() {
+        let mut info = Info::default();
+        info.set(info_id::TITLE, "Odd");
+        info.set(info_id::ARTIST, "KD");
+
+        let parsed = Info::parse(&info.to_bytes()).unwrap();
+        assert_eq!(Some("Odd"), parsed.get(info_id::TITLE));
+        assert_eq!(Some("KD"), parsed.get(info_id::ARTIST));
+        assert!(Info::parse(b"adtl").is_none());
+    }
+}
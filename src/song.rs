@@ -1,15 +1,11 @@
-use std::io::Write;
+use std::io::Read;
 
-use rand::Rng;
 use serde::{Deserialize, Serialize};
 
 use crate::constants;
 use crate::init;
 use crate::types;
 
-/// Length of characters of a filename to be generated
-const FILENAME_LENGTH: i32 = 16;
-
 #[derive(Clone, Debug, Default, Deserialize, Serialize, utoipa::ToSchema)]
 pub struct Song {
     #[serde(skip_serializing_if = "init::is_uuid_nil")]
@@ -45,6 +41,14 @@ pub struct Song {
     pub filename: String,
     #[serde(skip_serializing_if = "init::is_uuid_nil")]
     pub user_id: uuid::Uuid,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub audio_properties: Option<crate::audio_properties::AudioProperties>,
+    /// Hex encoded SHA-256 checksum of the song's file
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub checksum: String,
+    /// Hex encoded MD5 signature of the decoded audio, as embedded in FLAC files
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub audio_md5: String,
     #[serde(skip)]
     pub data: Vec<u8>,
     #[serde(skip)]
@@ -61,72 +65,312 @@ pub struct Song {
 }
 
 impl Song {
-    pub fn to_metadata_json(&self, pretty: bool) -> Result<String, serde_json::Error> {
+    /// Creates a Song from a FLAC file, filling its fields from the file's metadata
+    pub fn from_flac_file(directory: &str, filename: &str) -> Result<Song, crate::Error> {
+        let mut song = Song {
+            directory: String::from(directory),
+            filename: String::from(filename),
+            ..Default::default()
+        };
+
+        match song.path() {
+            Ok(path) => {
+                let file = std::fs::File::open(path)?;
+                let mut reader = std::io::BufReader::new(file);
+                crate::metadata::flac::read_song_metadata(&mut reader, &mut song)?;
+                Ok(song)
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Creates a Song from a FLAC, MP3 or WAV file, filling its fields from the file's metadata
+    pub fn from_file(directory: &str, filename: &str) -> Result<Song, crate::Error> {
+        let mut song = Song {
+            directory: String::from(directory),
+            filename: String::from(filename),
+            ..Default::default()
+        };
+
+        match io::read_metadata(&mut song) {
+            Ok(_) => Ok(song),
+            Err(err) => Err(err),
+        }
+    }
+
+    pub fn to_metadata_json(&self, pretty: bool) -> Result<String, crate::Error> {
         if pretty {
-            serde_json::to_string_pretty(&self)
+            Ok(serde_json::to_string_pretty(&self)?)
         } else {
-            serde_json::to_string(&self)
+            Ok(serde_json::to_string(&self)?)
         }
     }
 
-    /// Gets the path of a Song
-    pub fn song_path(&self) -> Result<String, std::io::Error> {
-        if self.directory.is_empty() {
-            return Err(std::io::Error::other(
-                crate::constants::error::DIRECTORY_NOT_INITIALIZED,
-            ));
-        } else if self.filename.is_empty() {
-            return Err(std::io::Error::other(
-                crate::constants::error::FILENAME_NOT_INITIALIZED,
-            ));
+    /// Gets the path of a Song. Filenames that would escape the song's directory are rejected
+    pub fn path(&self) -> Result<std::path::PathBuf, crate::Error> {
+        crate::util::path::join(&self.directory, &self.filename)
+    }
+
+    /// Gets the path of a Song as a string
+    pub fn song_path(&self) -> Result<String, crate::Error> {
+        match self.path() {
+            Ok(path) => Ok(path.to_string_lossy().into_owned()),
+            Err(err) => Err(err),
         }
+    }
 
-        let directory = &self.directory;
-        let last_index = directory.len() - 1;
+    /// Saves the song to the filesystem using the song's data
+    pub fn save_to_filesystem(&self) -> Result<(), crate::Error> {
+        self.save_to_filesystem_with_mode(types::WriteMode::Overwrite)
+    }
+
+    /// Saves the song to the filesystem atomically. Depending on the mode, an existing file is
+    /// either replaced or left untouched with an error returned
+    pub fn save_to_filesystem_with_mode(&self, mode: types::WriteMode) -> Result<(), crate::Error> {
+        self.save_to_storage(&crate::storage::LocalStorage, mode)
+    }
 
-        match crate::util::concatenate_path(directory, &self.filename, last_index) {
-            Ok(path) => Ok(path),
+    /// Saves the song's data to a storage backend under the song's path
+    pub fn save_to_storage<S: crate::storage::Storage>(
+        &self,
+        storage: &S,
+        mode: types::WriteMode,
+    ) -> Result<(), crate::Error> {
+        match self.path() {
+            Ok(song_path) => storage.put(&song_path, &self.data, mode),
             Err(err) => Err(err),
         }
     }
 
-    /// Saves the song to the filesystem using the song's data
-    pub fn save_to_filesystem(&self) -> Result<(), std::io::Error> {
-        match self.song_path() {
-            Ok(song_path) => match std::fs::File::create(&song_path) {
-                Ok(mut file) => match file.write_all(&self.data) {
-                    Ok(_res) => Ok(()),
-                    Err(err) => Err(err),
-                },
-                Err(err) => Err(err),
-            },
+    /// Saves the song to the filesystem by streaming from a reader instead of the song's data.
+    /// Returns the number of bytes written
+    pub fn save_from_reader<R: Read>(&self, reader: &mut R) -> Result<u64, crate::Error> {
+        self.save_from_reader_with_mode(reader, types::WriteMode::Overwrite)
+    }
+
+    /// Saves the song to the filesystem atomically by streaming from a reader. Returns the number
+    /// of bytes written
+    pub fn save_from_reader_with_mode<R: Read>(
+        &self,
+        reader: &mut R,
+        mode: types::WriteMode,
+    ) -> Result<u64, crate::Error> {
+        self.save_from_reader_to_storage(&crate::storage::LocalStorage, reader, mode)
+    }
+
+    /// Saves the song to a storage backend by streaming from a reader. Returns the number of bytes
+    /// written
+    pub fn save_from_reader_to_storage<S: crate::storage::Storage, R: Read>(
+        &self,
+        storage: &S,
+        reader: &mut R,
+        mode: types::WriteMode,
+    ) -> Result<u64, crate::Error> {
+        match self.path() {
+            Ok(song_path) => storage.put_from_reader(&song_path, reader, mode),
             Err(err) => Err(err),
         }
     }
 
-    /// Removes the song from the filesystem
-    pub fn remove_from_filesystem(&self) -> Result<(), std::io::Error> {
-        match self.song_path() {
-            Ok(song_path) => {
-                let p = std::path::Path::new(&song_path);
-                if p.exists() {
-                    match std::fs::remove_file(p) {
-                        Ok(_) => Ok(()),
-                        Err(err) => Err(err),
-                    }
-                } else {
-                    Err(std::io::Error::other(
-                        "Cannot delete file that does not exist",
-                    ))
-                }
+    /// Computes the duration of the song in seconds from its audio headers. The song's data is
+    /// used when loaded, otherwise the file at the song's path is read
+    pub fn calculate_duration(&self) -> Result<f64, crate::Error> {
+        self.calculate_duration_from_storage(&crate::storage::LocalStorage)
+    }
+
+    /// Computes the duration of the song in seconds from its audio headers, streaming the song
+    /// from a storage backend when its data is not loaded
+    pub fn calculate_duration_from_storage<S: crate::storage::Storage>(
+        &self,
+        storage: &S,
+    ) -> Result<f64, crate::Error> {
+        if !self.data.is_empty() {
+            return crate::metadata::duration(&mut std::io::Cursor::new(&self.data));
+        }
+
+        match self.path() {
+            Ok(path) => {
+                crate::metadata::duration(&mut std::io::BufReader::new(storage.open(&path)?))
             }
             Err(err) => Err(err),
         }
     }
+
+    /// Gets the technical properties of the song's audio from its audio headers. The song's data
+    /// is used when loaded, otherwise the file at the song's path is read
+    pub fn calculate_audio_properties(
+        &self,
+    ) -> Result<crate::audio_properties::AudioProperties, crate::Error> {
+        self.calculate_audio_properties_from_storage(&crate::storage::LocalStorage)
+    }
+
+    /// Gets the technical properties of the song's audio from its audio headers, streaming the
+    /// song from a storage backend when its data is not loaded
+    pub fn calculate_audio_properties_from_storage<S: crate::storage::Storage>(
+        &self,
+        storage: &S,
+    ) -> Result<crate::audio_properties::AudioProperties, crate::Error> {
+        if !self.data.is_empty() {
+            return crate::audio_properties::from_data(&self.data);
+        }
+
+        match self.path() {
+            Ok(path) => crate::metadata::audio_properties(&mut std::io::BufReader::new(
+                storage.open(&path)?,
+            )),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Computes the SHA-256 checksum of the song. The song's data is used when loaded, otherwise
+    /// the file at the song's path is read
+    pub fn compute_checksum(&self) -> Result<String, crate::Error> {
+        self.compute_checksum_from_storage(&crate::storage::LocalStorage)
+    }
+
+    /// Computes the SHA-256 checksum of the song. The song's data is used when loaded, otherwise
+    /// the song is streamed from a storage backend
+    pub fn compute_checksum_from_storage<S: crate::storage::Storage>(
+        &self,
+        storage: &S,
+    ) -> Result<String, crate::Error> {
+        if !self.data.is_empty() {
+            return Ok(crate::util::checksum::sha256(&self.data));
+        }
+
+        match self.path() {
+            Ok(path) => Ok(crate::util::checksum::sha256_from_reader(
+                &mut storage.open(&path)?,
+            )?),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Gets the MD5 signature of the decoded audio embedded in FLAC files. Empty when the song is
+    /// not a FLAC file or the encoder did not compute the signature
+    pub fn compute_audio_md5(&self) -> Result<String, crate::Error> {
+        self.compute_audio_md5_from_storage(&crate::storage::LocalStorage)
+    }
+
+    /// Gets the MD5 signature of the decoded audio embedded in FLAC files, streaming the song from
+    /// a storage backend when its data is not loaded
+    pub fn compute_audio_md5_from_storage<S: crate::storage::Storage>(
+        &self,
+        storage: &S,
+    ) -> Result<String, crate::Error> {
+        let blocks = if !self.data.is_empty() {
+            if detect_music_type(&self.data) != types::MusicType::FlacExtension {
+                return Ok(String::new());
+            }
+            crate::metadata::flac::read_blocks(&mut self.data.as_slice())?
+        } else {
+            let path = self.path()?;
+            let mut reader = std::io::BufReader::new(storage.open(&path)?);
+            let mut header: Vec<u8> = Vec::new();
+            std::io::Read::by_ref(&mut reader)
+                .take(constants::magic::FLAC.len() as u64)
+                .read_to_end(&mut header)?;
+            if detect_music_type(&header) != types::MusicType::FlacExtension {
+                return Ok(String::new());
+            }
+            std::io::Seek::seek(&mut reader, std::io::SeekFrom::Start(0))?;
+            crate::metadata::flac::read_blocks(&mut reader)?
+        };
+
+        let info = crate::metadata::flac::stream_info(&blocks)?;
+        if info.md5.iter().all(|byte| *byte == 0) {
+            Ok(String::new())
+        } else {
+            Ok(crate::util::checksum::to_hex(&info.md5))
+        }
+    }
+
+    /// Sets the song's checksum and audio MD5 signature
+    pub fn update_checksums(&mut self) -> Result<(), crate::Error> {
+        self.update_checksums_from_storage(&crate::storage::LocalStorage)
+    }
+
+    /// Sets the song's checksum and audio MD5, streaming the song from a storage backend when its
+    /// data is not loaded
+    pub fn update_checksums_from_storage<S: crate::storage::Storage>(
+        &mut self,
+        storage: &S,
+    ) -> Result<(), crate::Error> {
+        self.checksum = self.compute_checksum_from_storage(storage)?;
+        self.audio_md5 = self.compute_audio_md5_from_storage(storage)?;
+        Ok(())
+    }
+
+    /// Verifies the file at the song's path against the song's checksum. Returns false when the
+    /// file has changed
+    pub fn verify_file(&self) -> Result<bool, crate::Error> {
+        self.verify_file_in_storage(&crate::storage::LocalStorage)
+    }
+
+    /// Verifies the song stored in a storage backend against the song's checksum. Returns false
+    /// when the stored song has changed
+    pub fn verify_file_in_storage<S: crate::storage::Storage>(
+        &self,
+        storage: &S,
+    ) -> Result<bool, crate::Error> {
+        if self.checksum.is_empty() {
+            return Err(crate::Error::ChecksumNotSet);
+        }
+
+        match self.path() {
+            Ok(path) => Ok(
+                crate::util::checksum::sha256_from_reader(&mut storage.open(&path)?)?
+                    == self.checksum,
+            ),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Verifies that the song's data matches the extension of its filename
+    pub fn validate_data(&self) -> Result<types::MusicType, crate::Error> {
+        let extension = match std::path::Path::new(&self.filename).extension() {
+            Some(extension) => extension.to_string_lossy(),
+            None => return Err(crate::Error::DataDoesNotMatchExtension),
+        };
+
+        let detected = detect_music_type(&self.data);
+        if detected != types::MusicType::None
+            && detected == types::MusicType::from_extension(&extension)
+        {
+            Ok(detected)
+        } else {
+            Err(crate::Error::DataDoesNotMatchExtension)
+        }
+    }
+
+    /// Saves the song to the filesystem after verifying that the song's data matches the
+    /// extension of its filename
+    pub fn save_to_filesystem_validated(&self) -> Result<(), crate::Error> {
+        match self.validate_data() {
+            Ok(_) => self.save_to_filesystem(),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Removes the song from the filesystem
+    pub fn remove_from_filesystem(&self) -> Result<(), crate::Error> {
+        self.remove_from_storage(&crate::storage::LocalStorage)
+    }
+
+    /// Removes the song from a storage backend
+    pub fn remove_from_storage<S: crate::storage::Storage>(
+        &self,
+        storage: &S,
+    ) -> Result<(), crate::Error> {
+        match self.path() {
+            Ok(song_path) => storage.delete(&song_path),
+            Err(err) => Err(err),
+        }
+    }
 }
 
 /// Generates a filename. In order to save a song to the filesystem
-pub fn generate_filename(typ: types::MusicType, randomize: bool) -> Result<String, std::io::Error> {
+pub fn generate_filename(typ: types::MusicType, randomize: bool) -> Result<String, crate::Error> {
     let file_extension = match typ {
         types::MusicType::DefaultMusicExtension => {
             String::from(constants::file_extensions::audio::DEFAULTMUSICEXTENSION)
@@ -140,24 +384,11 @@ pub fn generate_filename(typ: types::MusicType, randomize: bool) -> Result<Strin
         types::MusicType::MPThreeExtension => {
             String::from(constants::file_extensions::audio::MPTHREEEXTENSION)
         }
-        types::MusicType::None => return Err(std::io::Error::other("Unsupported MusicTypes")),
+        types::MusicType::None => return Err(crate::Error::UnsupportedMusicType),
     };
 
     let filename: String = if randomize {
-        let mut filename: String = String::from("track-");
-        let some_chars: String = String::from("abcdefghij0123456789");
-        let some_chars_length = some_chars.len();
-        let mut rng = rand::rng();
-
-        for _ in 0..FILENAME_LENGTH {
-            let index = rng.random_range(0..=some_chars_length);
-            let rando_char = some_chars.chars().nth(index);
-
-            if let Some(c) = rando_char {
-                filename.push(c);
-            }
-        }
-        format!("{filename}{file_extension}")
+        crate::util::filename::Generator::new("track-").generate(&file_extension)?
     } else {
         format!("track-output{file_extension}")
     };
@@ -165,22 +396,48 @@ pub fn generate_filename(typ: types::MusicType, randomize: bool) -> Result<Strin
     Ok(filename)
 }
 
+/// Detects the MusicType of audio data from its leading bytes
+pub fn detect_music_type(data: &[u8]) -> types::MusicType {
+    if data.starts_with(constants::magic::FLAC) {
+        types::MusicType::FlacExtension
+    } else if data.starts_with(constants::magic::RIFF)
+        && data.get(8..12) == Some(constants::magic::WAVE)
+    {
+        types::MusicType::WavExtension
+    } else if data.starts_with(constants::magic::ID3)
+        || crate::metadata::mpeg::starts_with_frame(data)
+    {
+        // A leading ID3 tag is taken as MP3, matching how the metadata readers dispatch
+        types::MusicType::MPThreeExtension
+    } else {
+        types::MusicType::None
+    }
+}
+
 /// I/O operations for songs
 pub mod io {
-    use std::io::Read;
+    use std::io::{Read, Seek};
+
+    use crate::storage::Storage;
 
     /// Copies a song using the source song's data
     pub fn copy_song(
         song_source: &super::Song,
         song_target: &mut super::Song,
-    ) -> Result<(), std::io::Error> {
-        match song_target.song_path() {
+    ) -> Result<(), crate::Error> {
+        copy_song_in_storage(&crate::storage::LocalStorage, song_source, song_target)
+    }
+
+    /// Copies a song into a storage backend using the source song's data
+    pub fn copy_song_in_storage<S: Storage>(
+        storage: &S,
+        song_source: &super::Song,
+        song_target: &mut super::Song,
+    ) -> Result<(), crate::Error> {
+        match song_target.path() {
             Ok(songpath) => {
-                let p = std::path::Path::new(&songpath);
-                if p.exists() {
-                    Err(std::io::Error::other(
-                        "Cannot copy song over to one that already exists",
-                    ))
+                if storage.exists(&songpath)? {
+                    Err(crate::Error::FileAlreadyExists)
                 } else {
                     if song_target.data.is_empty() {
                         song_target.data = song_source.data.clone();
@@ -189,26 +446,165 @@ pub mod io {
                         song_target.data = song_source.data.clone();
                     }
 
-                    match song_target.save_to_filesystem() {
-                        Ok(_) => Ok(()),
-                        Err(err) => Err(err),
-                    }
+                    song_target.save_to_storage(storage, crate::types::WriteMode::CreateNew)
                 }
             }
             Err(err) => Err(err),
         }
     }
 
+    /// Copies the file of a song by streaming it, without loading it into the source song's data.
+    /// Returns the number of bytes copied
+    pub fn copy_song_file(
+        song_source: &super::Song,
+        song_target: &super::Song,
+    ) -> Result<u64, crate::Error> {
+        copy_song_file_in_storage(&crate::storage::LocalStorage, song_source, song_target)
+    }
+
+    /// Copies a song within a storage backend by streaming it. Returns the number of bytes copied
+    pub fn copy_song_file_in_storage<S: Storage>(
+        storage: &S,
+        song_source: &super::Song,
+        song_target: &super::Song,
+    ) -> Result<u64, crate::Error> {
+        match song_target.path() {
+            Ok(songpath) => {
+                if storage.exists(&songpath)? {
+                    Err(crate::Error::FileAlreadyExists)
+                } else {
+                    let mut reader =
+                        std::io::BufReader::new(open_reader_from_storage(storage, song_source)?);
+                    song_target.save_from_reader_to_storage(
+                        storage,
+                        &mut reader,
+                        crate::types::WriteMode::CreateNew,
+                    )
+                }
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Opens the file of a song for reading
+    pub fn open_reader(song: &super::Song) -> Result<std::fs::File, crate::Error> {
+        open_reader_from_storage(&crate::storage::LocalStorage, song)
+    }
+
+    /// Opens a song stored in a storage backend for reading
+    pub fn open_reader_from_storage<S: Storage>(
+        storage: &S,
+        song: &super::Song,
+    ) -> Result<S::Reader, crate::Error> {
+        match song.path() {
+            Ok(path) => storage.open(&path),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Gets the size in bytes of the file of a song
+    pub fn file_size(song: &super::Song) -> Result<u64, crate::Error> {
+        file_size_from_storage(&crate::storage::LocalStorage, song)
+    }
+
+    /// Gets the size in bytes of a song stored in a storage backend
+    pub fn file_size_from_storage<S: Storage>(
+        storage: &S,
+        song: &super::Song,
+    ) -> Result<u64, crate::Error> {
+        match song.path() {
+            Ok(path) => storage.size(&path),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Opens a reader over a range of the bytes of the file of a song, such as for an HTTP Range
+    /// request. The range is inclusive of `start` and exclusive of `end`. An `end` of None or past
+    /// the end of the file reads to the end of the file
+    pub fn open_range(
+        song: &super::Song,
+        start: u64,
+        end: Option<u64>,
+    ) -> Result<std::io::Take<std::fs::File>, crate::Error> {
+        open_range_from_storage(&crate::storage::LocalStorage, song, start, end)
+    }
+
+    /// Opens a reader over a range of the bytes of a song stored in a storage backend, like
+    /// `open_range`
+    pub fn open_range_from_storage<S: Storage>(
+        storage: &S,
+        song: &super::Song,
+        start: u64,
+        end: Option<u64>,
+    ) -> Result<std::io::Take<S::Reader>, crate::Error> {
+        let size = file_size_from_storage(storage, song)?;
+        let end = match end {
+            Some(end) => end.min(size),
+            None => size,
+        };
+
+        if start >= end {
+            return Err(crate::Error::RangeNotSatisfiable);
+        }
+
+        let mut reader = open_reader_from_storage(storage, song)?;
+        reader.seek(std::io::SeekFrom::Start(start))?;
+        Ok(reader.take(end - start))
+    }
+
+    /// Fills the song's fields from the metadata embedded in the file at the song's path. FLAC,
+    /// MP3 and WAV files are supported
+    pub fn read_metadata(song: &mut super::Song) -> Result<(), crate::Error> {
+        read_metadata_from_storage(&crate::storage::LocalStorage, song)
+    }
+
+    /// Fills the song's fields from the metadata embedded in a song stored in a storage backend
+    pub fn read_metadata_from_storage<S: Storage>(
+        storage: &S,
+        song: &mut super::Song,
+    ) -> Result<(), crate::Error> {
+        match song.path() {
+            Ok(path) => {
+                let mut reader = std::io::BufReader::new(storage.open(&path)?);
+                crate::metadata::read_song_metadata(&mut reader, song)
+            }
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Writes the song's fields into the metadata embedded in the file at the song's path. FLAC,
+    /// MP3 and WAV files are supported
+    pub fn write_metadata(song: &super::Song) -> Result<(), crate::Error> {
+        write_metadata_to_storage(&crate::storage::LocalStorage, song)
+    }
+
+    /// Writes the song's fields into the metadata embedded in a song stored in a storage backend
+    pub fn write_metadata_to_storage<S: Storage>(
+        storage: &S,
+        song: &super::Song,
+    ) -> Result<(), crate::Error> {
+        match song.path() {
+            Ok(path) => crate::metadata::write_song_metadata(storage, &path, song),
+            Err(err) => Err(err),
+        }
+    }
+
     /// Gets the raw file data of a song from the filesystem
-    pub fn to_data(song: &super::Song) -> Result<Vec<u8>, std::io::Error> {
-        match song.song_path() {
+    pub fn to_data(song: &super::Song) -> Result<Vec<u8>, crate::Error> {
+        to_data_from_storage(&crate::storage::LocalStorage, song)
+    }
+
+    /// Gets the raw file data of a song from a storage backend
+    pub fn to_data_from_storage<S: Storage>(
+        storage: &S,
+        song: &super::Song,
+    ) -> Result<Vec<u8>, crate::Error> {
+        match song.path() {
             Ok(path) => {
-                let mut file = std::fs::File::open(path)?;
-                let mut buffer: Vec<u8> = Vec::new();
-                file.read_to_end(&mut buffer)?;
+                let buffer = storage.get(&path)?;
 
                 if buffer.is_empty() {
-                    Err(std::io::Error::other("File is empty"))
+                    Err(crate::Error::FileEmpty)
                 } else {
                     Ok(buffer)
                 }
@@ -217,3 +613,444 @@ pub mod io {
         }
     }
 }
+
+/// Detection of duplicate songs
+pub mod dedupe {
+    use std::collections::HashMap;
+
+    /// Probable duplicates can differ in duration by this many seconds
+    pub const DURATION_TOLERANCE: i32 = 2;
+
+    /// Why songs were grouped together
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub enum MatchReason {
+        /// The files are byte for byte identical
+        Checksum,
+        /// The decoded audio is identical, but the files differ such as by their tags
+        AudioMd5,
+        /// Title, artist and album match after normalizing and the durations are close
+        Metadata,
+    }
+
+    /// Songs considered duplicates of each other
+    #[derive(Clone, Debug)]
+    pub struct Cluster {
+        pub reason: MatchReason,
+        pub songs: Vec<super::Song>,
+    }
+
+    /// Songs grouped by how confidently they are duplicates. Every song ends up in exactly one
+    /// of the groups
+    #[derive(Clone, Debug, Default)]
+    pub struct Duplicates {
+        /// Songs with the same checksum or audio MD5 signature
+        pub exact: Vec<Cluster>,
+        /// Songs with matching metadata that are not exact duplicates of any other song
+        pub probable: Vec<Cluster>,
+        /// Songs without duplicates
+        pub unique: Vec<super::Song>,
+    }
+
+    /// Groups songs into exact and probable duplicates. The checksum and audio MD5 signature of
+    /// the songs should be set beforehand for exact duplicates to be found
+    pub fn group(songs: Vec<super::Song>) -> Duplicates {
+        let mut parents: Vec<usize> = (0..songs.len()).collect();
+        let mut by_audio_md5 = vec![false; songs.len()];
+
+        join_by_key(&songs, &mut parents, |song| &song.checksum);
+        let checksum_roots: Vec<usize> = (0..songs.len()).map(|i| find(&mut parents, i)).collect();
+        join_by_key(&songs, &mut parents, |song| &song.audio_md5);
+        for i in 0..songs.len() {
+            let root = find(&mut parents, i);
+            if checksum_roots[i] != checksum_roots[root] {
+                by_audio_md5[root] = true;
+            }
+        }
+
+        let mut exact: Vec<(MatchReason, Vec<usize>)> = Vec::new();
+        let mut remaining: Vec<usize> = Vec::new();
+        for (root, members) in members_by_root(&mut parents) {
+            if members.len() > 1 {
+                let reason = if by_audio_md5[root] {
+                    MatchReason::AudioMd5
+                } else {
+                    MatchReason::Checksum
+                };
+                exact.push((reason, members));
+            } else {
+                remaining.extend(members);
+            }
+        }
+
+        let (probable, unique) = group_by_metadata(&songs, remaining);
+
+        let mut songs: Vec<Option<super::Song>> = songs.into_iter().map(Some).collect();
+        let mut take = |indices: Vec<usize>| -> Vec<super::Song> {
+            indices
+                .into_iter()
+                .filter_map(|i| songs[i].take())
+                .collect()
+        };
+
+        Duplicates {
+            exact: exact
+                .into_iter()
+                .map(|(reason, indices)| Cluster {
+                    reason,
+                    songs: take(indices),
+                })
+                .collect(),
+            probable: probable
+                .into_iter()
+                .map(|indices| Cluster {
+                    reason: MatchReason::Metadata,
+                    songs: take(indices),
+                })
+                .collect(),
+            unique: take(unique),
+        }
+    }
+
+    /// Normalizes a tag value for comparison. Case, punctuation and extra whitespace are ignored
+    pub fn normalize(value: &str) -> String {
+        value
+            .to_lowercase()
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|word| !word.is_empty())
+            .collect::<Vec<&str>>()
+            .join(" ")
+    }
+
+    /// Groups the songs at the given indices by their metadata. Returns the clusters and the
+    /// indices left without a match
+    fn group_by_metadata(
+        songs: &[super::Song],
+        indices: Vec<usize>,
+    ) -> (Vec<Vec<usize>>, Vec<usize>) {
+        let mut by_key: HashMap<(String, String, String), Vec<usize>> = HashMap::new();
+        let mut unique: Vec<usize> = Vec::new();
+
+        for i in indices {
+            let song = &songs[i];
+            let key = (
+                normalize(&song.title),
+                normalize(&song.artist),
+                normalize(&song.album),
+            );
+            if key.0.is_empty() {
+                unique.push(i);
+                continue;
+            }
+
+            by_key.entry(key).or_default().push(i);
+        }
+
+        let mut clusters: Vec<Vec<usize>> = Vec::new();
+        for (_, mut members) in by_key {
+            members.sort_by_key(|i| songs[*i].duration);
+
+            // Durations are compared against the shortest song of the cluster, so songs that are
+            // each close to the next do not chain into one cluster
+            let mut cluster: Vec<usize> = Vec::new();
+            for i in members {
+                if let Some(first) = cluster.first()
+                    && songs[i].duration - songs[*first].duration > DURATION_TOLERANCE
+                {
+                    flush(&mut cluster, &mut clusters, &mut unique);
+                }
+                cluster.push(i);
+            }
+            flush(&mut cluster, &mut clusters, &mut unique);
+        }
+
+        for cluster in clusters.iter_mut() {
+            cluster.sort();
+        }
+        clusters.sort_by_key(|cluster| cluster[0]);
+        unique.sort();
+
+        (clusters, unique)
+    }
+
+    /// Moves a cluster being built to the clusters, or to the unique indices when it only has one
+    fn flush(cluster: &mut Vec<usize>, clusters: &mut Vec<Vec<usize>>, unique: &mut Vec<usize>) {
+        match cluster.len() {
+            0 => {}
+            1 => unique.append(cluster),
+            _ => clusters.push(std::mem::take(cluster)),
+        }
+    }
+
+    /// Joins the songs sharing a non-empty key
+    fn join_by_key<F: Fn(&super::Song) -> &String>(
+        songs: &[super::Song],
+        parents: &mut [usize],
+        key: F,
+    ) {
+        let mut first: HashMap<&String, usize> = HashMap::new();
+
+        for (i, song) in songs.iter().enumerate() {
+            let value = key(song);
+            if value.is_empty() {
+                continue;
+            }
+
+            match first.get(value) {
+                Some(j) => {
+                    let (a, b) = (find(parents, i), find(parents, *j));
+                    if a != b {
+                        parents[a.max(b)] = a.min(b);
+                    }
+                }
+                None => {
+                    first.insert(value, i);
+                }
+            }
+        }
+    }
+
+    /// Finds the index representing the group of an index
+    fn find(parents: &mut [usize], i: usize) -> usize {
+        let mut root = i;
+        while parents[root] != root {
+            root = parents[root];
+        }
+
+        let mut current = i;
+        while parents[current] != root {
+            let next = parents[current];
+            parents[current] = root;
+            current = next;
+        }
+
+        root
+    }
+
+    /// Gets the members of each group, in the order of their first member. The root of a group
+    /// is always its first member
+    fn members_by_root(parents: &mut [usize]) -> Vec<(usize, Vec<usize>)> {
+        let mut groups: HashMap<usize, Vec<usize>> = HashMap::new();
+
+        for i in 0..parents.len() {
+            let root = find(parents, i);
+            groups.entry(root).or_default().push(i);
+        }
+
+        let mut groups: Vec<(usize, Vec<usize>)> = groups.into_iter().collect();
+        groups.sort_by_key(|(root, _)| *root);
+        groups
+    }
+}
+
+/// Human readable filenames built from a song's fields
+pub mod template {
+    use std::path::{Path, PathBuf};
+
+    /// Lays songs out by artist and album
+    pub const DEFAULT_TEMPLATE: &str =
+        "{album_artist}/{album} ({year})/{disc:02}-{track:02} {title}{ext}";
+    /// Longest path component in bytes that common filesystems accept
+    pub const MAX_COMPONENT_LENGTH: usize = 255;
+    /// Stands in for empty fields
+    pub const UNKNOWN: &str = "Unknown";
+
+    /// Characters that are illegal in filenames on common filesystems
+    const ILLEGAL_CHARACTERS: &[char] = &['<', '>', ':', '"', '/', '\\', '|', '?', '*'];
+    /// Names Windows reserves for devices
+    const RESERVED_NAMES: &[&str] = &[
+        "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+        "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+    ];
+
+    /// Renders a template into a relative path for a song. Placeholders are written as `{name}`,
+    /// or `{name:0N}` to zero pad numbers to N digits. `/` in the template separates directories.
+    ///
+    /// The placeholders are title, artist, album, album_artist, genre, year, track, track_count,
+    /// disc, disc_count and ext, the extension of the song's filename including the dot. An
+    /// empty album_artist falls back to the artist, and other empty text is rendered as Unknown
+    pub fn render(template: &str, song: &super::Song) -> Result<PathBuf, crate::Error> {
+        let mut path = PathBuf::new();
+
+        for component in template.split('/') {
+            let rendered = render_component(component, song)?;
+            path.push(sanitize(&rendered));
+        }
+
+        if path.as_os_str().is_empty() {
+            Err(crate::Error::InvalidTemplate)
+        } else {
+            Ok(path)
+        }
+    }
+
+    /// Renders a template for each song. Songs that would share a path get a number appended,
+    /// such as `01 Title (2).flac`. Paths are compared ignoring case, as some filesystems do
+    pub fn render_all(template: &str, songs: &[super::Song]) -> Result<Vec<PathBuf>, crate::Error> {
+        let mut used: std::collections::HashSet<String> = std::collections::HashSet::new();
+        let mut paths: Vec<PathBuf> = Vec::new();
+
+        for song in songs {
+            let path = render(template, song)?;
+            let mut candidate = path.clone();
+            let mut count = 1;
+
+            while !used.insert(candidate.to_string_lossy().to_lowercase()) {
+                count += 1;
+                candidate = with_suffix(&path, &format!(" ({count})"));
+            }
+
+            paths.push(candidate);
+        }
+
+        Ok(paths)
+    }
+
+    /// Makes text safe to use as a path component. Illegal and control characters are replaced,
+    /// trailing dots and spaces are removed, reserved names are altered, and long text is
+    /// truncated while keeping the extension
+    pub fn sanitize(component: &str) -> String {
+        let replaced: String = component
+            .chars()
+            .map(|c| {
+                if c.is_control() || ILLEGAL_CHARACTERS.contains(&c) {
+                    '_'
+                } else {
+                    c
+                }
+            })
+            .collect();
+
+        let mut sanitized = String::from(replaced.trim().trim_end_matches(['.', ' ']));
+        if sanitized.is_empty() || sanitized.chars().all(|c| c == '.') {
+            sanitized = String::from("_");
+        }
+
+        let stem = match sanitized.split_once('.') {
+            Some((stem, _)) => stem,
+            None => &sanitized,
+        };
+        if RESERVED_NAMES
+            .iter()
+            .any(|name| name.eq_ignore_ascii_case(stem))
+        {
+            sanitized.insert(stem.len(), '_');
+        }
+
+        truncate(&sanitized, MAX_COMPONENT_LENGTH)
+    }
+
+    fn render_component(component: &str, song: &super::Song) -> Result<String, crate::Error> {
+        let mut rendered = String::new();
+        let mut rest = component;
+
+        while let Some(start) = rest.find('{') {
+            rendered.push_str(&rest[..start]);
+            let end = match rest[start..].find('}') {
+                Some(end) => start + end,
+                None => return Err(crate::Error::InvalidTemplate),
+            };
+
+            let placeholder = &rest[start + 1..end];
+            let (name, width) = match placeholder.split_once(':') {
+                Some((name, spec)) => match spec.parse::<usize>() {
+                    Ok(width) => (name, width),
+                    Err(_) => return Err(crate::Error::InvalidTemplate),
+                },
+                None => (placeholder, 0),
+            };
+
+            let value = value_of(name, width, song)?;
+            // Fields may hold separators, such as an artist named AC/DC
+            rendered.push_str(&value.replace(['/', '\\'], "_"));
+            rest = &rest[end + 1..];
+        }
+
+        if rest.contains('}') {
+            return Err(crate::Error::InvalidTemplate);
+        }
+        rendered.push_str(rest);
+
+        Ok(rendered)
+    }
+
+    fn value_of(name: &str, width: usize, song: &super::Song) -> Result<String, crate::Error> {
+        let text = |value: &str| {
+            if value.trim().is_empty() {
+                String::from(UNKNOWN)
+            } else {
+                String::from(value)
+            }
+        };
+        let number = |value: i32| format!("{value:0width$}");
+
+        match name {
+            "title" => Ok(text(&song.title)),
+            "artist" => Ok(text(&song.artist)),
+            "album" => Ok(text(&song.album)),
+            "album_artist" if song.album_artist.trim().is_empty() => Ok(text(&song.artist)),
+            "album_artist" => Ok(text(&song.album_artist)),
+            "genre" => Ok(text(&song.genre)),
+            "year" => Ok(number(song.year)),
+            "track" => Ok(number(song.track)),
+            "track_count" => Ok(number(song.track_count)),
+            "disc" => Ok(number(song.disc)),
+            "disc_count" => Ok(number(song.disc_count)),
+            "ext" => Ok(extension(song)),
+            _ => Err(crate::Error::InvalidTemplate),
+        }
+    }
+
+    /// Gets the extension of the song's filename, falling back to the song's audio type
+    fn extension(song: &super::Song) -> String {
+        match Path::new(&song.filename).extension() {
+            Some(extension) => format!(".{}", extension.to_string_lossy()),
+            None if !song.audio_type.is_empty() => {
+                format!(".{}", song.audio_type.trim_start_matches('.'))
+            }
+            None => String::new(),
+        }
+    }
+
+    /// Appends a suffix to the last component of a path, before its extension
+    fn with_suffix(path: &Path, suffix: &str) -> PathBuf {
+        let filename = match path.file_name() {
+            Some(filename) => filename.to_string_lossy().into_owned(),
+            None => String::new(),
+        };
+        let length = MAX_COMPONENT_LENGTH.saturating_sub(suffix.len());
+        let (stem, extension) = match split_extension(&filename) {
+            (stem, extension) if extension.len() < length / 2 => (stem, extension),
+            _ => (filename.as_str(), ""),
+        };
+
+        let stem = truncate(stem, length - extension.len());
+        path.with_file_name(format!("{stem}{suffix}{extension}"))
+    }
+
+    /// Truncates text to a length in bytes on a character boundary, keeping the extension
+    fn truncate(component: &str, length: usize) -> String {
+        if component.len() <= length {
+            return String::from(component);
+        }
+
+        let (stem, extension) = match split_extension(component) {
+            (stem, extension) if extension.len() < length / 2 => (stem, extension),
+            _ => (component, ""),
+        };
+
+        let mut end = length - extension.len();
+        while !stem.is_char_boundary(end.min(stem.len())) {
+            end -= 1;
+        }
+
+        format!("{}{extension}", stem[..end.min(stem.len())].trim_end())
+    }
+
+    /// Splits a filename into its stem and its extension including the dot
+    fn split_extension(filename: &str) -> (&str, &str) {
+        match filename.rfind('.') {
+            Some(index) if index > 0 => filename.split_at(index),
+            _ => (filename, ""),
+        }
+    }
+}
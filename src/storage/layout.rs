@@ -0,0 +1,262 @@
+//! Sharded storage layout. Files are spread over nested directories named after the leading
+//! characters of a song's id or checksum, such as `ab/cd/abcdef….flac`, so no single directory
+//! holds every file
+
+use std::path::{Path, PathBuf};
+
+/// Number of characters in the name of each shard directory
+pub const DEFAULT_WIDTH: usize = 2;
+/// Number of nested shard directories
+pub const DEFAULT_DEPTH: usize = 2;
+
+/// What the storage path of a song is derived from
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Key {
+    #[default]
+    Id,
+    /// The song's checksum. Identical files share a path
+    Checksum,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Layout {
+    pub root: PathBuf,
+    pub key: Key,
+    pub width: usize,
+    pub depth: usize,
+}
+
+impl Layout {
+    /// Creates a layout with the default shard width and depth
+    pub fn new<P: AsRef<Path>>(root: P, key: Key) -> Layout {
+        Layout {
+            root: root.as_ref().to_path_buf(),
+            key,
+            width: DEFAULT_WIDTH,
+            depth: DEFAULT_DEPTH,
+        }
+    }
+
+    /// Gets the shard directory and filename for a lowercase hex name such as an id or checksum
+    pub fn locate(
+        &self,
+        name: &str,
+        extension: Option<&str>,
+    ) -> Result<(PathBuf, String), crate::Error> {
+        if name.len() < self.width * self.depth || !crate::util::checksum::is_lowercase_hex(name) {
+            return Err(crate::Error::InvalidFilename);
+        }
+
+        let mut directory = self.root.clone();
+        for level in 0..self.depth {
+            directory.push(&name[level * self.width..(level + 1) * self.width]);
+        }
+
+        let filename = match extension {
+            Some(extension) if !extension.is_empty() => format!("{name}.{extension}"),
+            _ => String::from(name),
+        };
+        crate::util::path::validate_filename(&filename)?;
+
+        Ok((directory, filename))
+    }
+
+    /// Gets the shard directory and filename of a song. The extension of the song's current
+    /// filename is kept
+    pub fn locate_song(&self, song: &crate::song::Song) -> Result<(PathBuf, String), crate::Error> {
+        let name = match self.key {
+            Key::Id => {
+                if song.id.is_nil() {
+                    return Err(crate::Error::IdNotInitialized);
+                }
+                song.id.simple().to_string()
+            }
+            Key::Checksum => {
+                if song.checksum.is_empty() {
+                    return Err(crate::Error::ChecksumNotSet);
+                }
+                let checksum = song.checksum.to_ascii_lowercase();
+                if !crate::util::checksum::is_sha256_hex(&checksum) {
+                    return Err(crate::Error::InvalidChecksum);
+                }
+                checksum
+            }
+        };
+
+        self.locate(&name, extension(&song.filename))
+    }
+
+    /// Gets the shard directory and filename of a coverart, which is always derived from its id
+    pub fn locate_coverart(
+        &self,
+        coverart: &crate::coverart::CoverArt,
+    ) -> Result<(PathBuf, String), crate::Error> {
+        if coverart.id.is_nil() {
+            return Err(crate::Error::IdNotInitialized);
+        }
+
+        let extension = match extension(&coverart.filename) {
+            Some(extension) => Some(extension),
+            None if !coverart.file_type.is_empty() => Some(coverart.file_type.as_str()),
+            None => None,
+        };
+        self.locate(&coverart.id.simple().to_string(), extension)
+    }
+
+    /// Resolves the path of a song within the layout
+    pub fn resolve_song(&self, song: &crate::song::Song) -> Result<PathBuf, crate::Error> {
+        let (directory, filename) = self.locate_song(song)?;
+        Ok(directory.join(filename))
+    }
+
+    /// Resolves the path of a coverart within the layout
+    pub fn resolve_coverart(
+        &self,
+        coverart: &crate::coverart::CoverArt,
+    ) -> Result<PathBuf, crate::Error> {
+        let (directory, filename) = self.locate_coverart(coverart)?;
+        Ok(directory.join(filename))
+    }
+
+    /// Points the song's directory and filename at its location within the layout, creating the
+    /// shard directories so the song can be saved
+    pub fn place_song(&self, song: &mut crate::song::Song) -> Result<(), crate::Error> {
+        let (directory, filename) = self.locate_song(song)?;
+        std::fs::create_dir_all(&directory)?;
+        song.directory = directory.to_string_lossy().into_owned();
+        song.filename = filename;
+        Ok(())
+    }
+
+    /// Points the coverart's directory and filename at its location within the layout, creating
+    /// the shard directories so the coverart can be saved
+    pub fn place_coverart(
+        &self,
+        coverart: &mut crate::coverart::CoverArt,
+    ) -> Result<(), crate::Error> {
+        let (directory, filename) = self.locate_coverart(coverart)?;
+        std::fs::create_dir_all(&directory)?;
+        coverart.directory = directory.to_string_lossy().into_owned();
+        coverart.filename = filename;
+        Ok(())
+    }
+
+    /// Moves the file of a song from its current directory and filename into the layout, and
+    /// updates the song to point at it
+    pub fn migrate_song(&self, song: &mut crate::song::Song) -> Result<(), crate::Error> {
+        let source = song.path()?;
+        let (directory, filename) = self.locate_song(song)?;
+        self.move_file(&source, &directory, &filename)?;
+        song.directory = directory.to_string_lossy().into_owned();
+        song.filename = filename;
+        Ok(())
+    }
+
+    /// Moves the file of a coverart from its current directory and filename into the layout, and
+    /// updates the coverart to point at it
+    pub fn migrate_coverart(
+        &self,
+        coverart: &mut crate::coverart::CoverArt,
+    ) -> Result<(), crate::Error> {
+        let source = coverart.path()?;
+        let (directory, filename) = self.locate_coverart(coverart)?;
+        self.move_file(&source, &directory, &filename)?;
+        coverart.directory = directory.to_string_lossy().into_owned();
+        coverart.filename = filename;
+        Ok(())
+    }
+
+    /// Lists the paths of the files stored in the layout
+    pub fn enumerate(&self) -> Result<Vec<PathBuf>, crate::Error> {
+        let mut paths: Vec<PathBuf> = Vec::new();
+        if self.root.is_dir() {
+            super::collect_files(&self.root, Some(self.depth), &mut paths)?;
+        }
+
+        paths.sort();
+        Ok(paths)
+    }
+
+    fn move_file(
+        &self,
+        source: &Path,
+        directory: &Path,
+        filename: &str,
+    ) -> Result<(), crate::Error> {
+        let target = directory.join(filename);
+        if source == target {
+            return Ok(());
+        } else if !source.exists() {
+            return Err(crate::Error::FileNotFound);
+        }
+
+        if target.exists() {
+            // Content addressed files with the same name should hold the same data, but the
+            // source is only dropped once that is confirmed
+            return if self.key == Key::Checksum
+                && crate::util::checksum::sha256_from_file(&target)?
+                    == crate::util::checksum::sha256_from_file(source)?
+            {
+                Ok(std::fs::remove_file(source)?)
+            } else {
+                Err(crate::Error::FileAlreadyExists)
+            };
+        }
+
+        std::fs::create_dir_all(directory)?;
+        if std::fs::rename(source, &target).is_err() {
+            // Renaming fails across filesystems
+            let mut reader = std::fs::File::open(source)?;
+            crate::util::atomic::write_from_reader(
+                &target,
+                &mut reader,
+                crate::types::WriteMode::CreateNew,
+            )?;
+            std::fs::remove_file(source)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn extension(filename: &str) -> Option<&str> {
+    Path::new(filename)
+        .extension()
+        .and_then(|extension| extension.to_str())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_locate() {
+        let layout = super::Layout::new("/music", super::Key::Id);
+        let (directory, filename) = layout.locate("abcdef0123", Some("flac")).unwrap();
+        assert_eq!(PathBuf::from("/music/ab/cd"), directory);
+        assert_eq!("abcdef0123.flac", filename);
+
+        assert!(layout.locate("abc", Some("flac")).is_err());
+        assert!(layout.locate("abcdef", Some("../x")).is_err());
+        assert!(layout.locate("../../etc", None).is_err());
+        assert!(layout.locate("ABCDEF", None).is_err());
+    }
+
+    #[test]
+    fn test_locate_invalid_checksum() {
+        let layout = super::Layout::new("/music", super::Key::Checksum);
+        let mut song = crate::song::Song {
+            filename: String::from("track01.flac"),
+            checksum: String::from("ab/../../etc/passwd"),
+            ..Default::default()
+        };
+        assert!(matches!(
+            layout.locate_song(&song),
+            Err(crate::Error::InvalidChecksum)
+        ));
+
+        song.checksum = crate::util::checksum::sha256(b"abc").to_uppercase();
+        let (directory, _) = layout.locate_song(&song).unwrap();
+        assert_eq!(PathBuf::from("/music/ba/78"), directory);
+    }
+}
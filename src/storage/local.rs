@@ -0,0 +1,89 @@
+//! Storage on the local filesystem
+
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+/// Stores files on the local filesystem. Keys are used as filesystem paths and writes are atomic,
+/// except for `write_at` which changes the file in place
+#[derive(Clone, Copy, Debug, Default)]
+pub struct LocalStorage;
+
+impl super::Storage for LocalStorage {
+    type Reader = std::fs::File;
+
+    fn put(
+        &self,
+        key: &Path,
+        data: &[u8],
+        mode: crate::types::WriteMode,
+    ) -> Result<(), crate::Error> {
+        crate::util::atomic::write(key, data, mode)
+    }
+
+    fn put_from_reader<R: Read>(
+        &self,
+        key: &Path,
+        reader: &mut R,
+        mode: crate::types::WriteMode,
+    ) -> Result<u64, crate::Error> {
+        crate::util::atomic::write_from_reader(key, reader, mode)
+    }
+
+    fn get(&self, key: &Path) -> Result<Vec<u8>, crate::Error> {
+        Ok(std::fs::read(key)?)
+    }
+
+    fn open(&self, key: &Path) -> Result<std::fs::File, crate::Error> {
+        Ok(std::fs::File::open(key)?)
+    }
+
+    fn size(&self, key: &Path) -> Result<u64, crate::Error> {
+        Ok(std::fs::metadata(key)?.len())
+    }
+
+    fn write_at(&self, key: &Path, offset: u64, data: &[u8]) -> Result<(), crate::Error> {
+        let mut file = std::fs::OpenOptions::new().write(true).open(key)?;
+        file.seek(SeekFrom::Start(offset))?;
+        file.write_all(data)?;
+        Ok(file.sync_all()?)
+    }
+
+    /// The output is written to a temporary file that is renamed over the original, so a failure
+    /// never leaves a partially written file behind. The file keeps its permissions
+    fn rewrite<F>(&self, key: &Path, write: F) -> Result<(), crate::Error>
+    where
+        F: FnOnce(&mut std::fs::File, &mut dyn Write) -> Result<(), std::io::Error>,
+    {
+        let mut source = std::fs::File::open(key)?;
+        let permissions = source.metadata()?.permissions();
+
+        crate::util::atomic::write_with(key, crate::types::WriteMode::Overwrite, |writer| {
+            write(&mut source, writer)
+        })?;
+        Ok(std::fs::set_permissions(key, permissions)?)
+    }
+
+    fn delete(&self, key: &Path) -> Result<(), crate::Error> {
+        if key.exists() {
+            Ok(std::fs::remove_file(key)?)
+        } else {
+            Err(crate::Error::FileNotFound)
+        }
+    }
+
+    fn exists(&self, key: &Path) -> Result<bool, crate::Error> {
+        Ok(key.try_exists()?)
+    }
+
+    fn list(&self, prefix: &Path) -> Result<Vec<PathBuf>, crate::Error> {
+        let mut keys: Vec<PathBuf> = Vec::new();
+        if prefix.is_dir() {
+            super::collect_files(prefix, None, &mut keys)?;
+        } else if prefix.is_file() && !super::is_hidden(prefix) {
+            keys.push(prefix.to_path_buf());
+        }
+
+        keys.sort();
+        Ok(keys)
+    }
+}
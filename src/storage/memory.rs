@@ -0,0 +1,146 @@
+//! Storage in memory, for tests
+
+use std::collections::BTreeMap;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Stores files in memory
+#[derive(Debug, Default)]
+pub struct MemoryStorage {
+    files: Mutex<BTreeMap<PathBuf, Vec<u8>>>,
+}
+
+impl MemoryStorage {
+    pub fn new() -> MemoryStorage {
+        MemoryStorage::default()
+    }
+
+    fn files(&self) -> std::sync::MutexGuard<'_, BTreeMap<PathBuf, Vec<u8>>> {
+        crate::util::sync::lock(&self.files)
+    }
+}
+
+impl super::Storage for MemoryStorage {
+    type Reader = std::io::Cursor<Vec<u8>>;
+
+    fn put(
+        &self,
+        key: &Path,
+        data: &[u8],
+        mode: crate::types::WriteMode,
+    ) -> Result<(), crate::Error> {
+        let mut files = self.files();
+        if mode == crate::types::WriteMode::CreateNew && files.contains_key(key) {
+            return Err(crate::Error::FileAlreadyExists);
+        }
+
+        files.insert(key.to_path_buf(), data.to_vec());
+        Ok(())
+    }
+
+    fn put_from_reader<R: Read>(
+        &self,
+        key: &Path,
+        reader: &mut R,
+        mode: crate::types::WriteMode,
+    ) -> Result<u64, crate::Error> {
+        let mut data: Vec<u8> = Vec::new();
+        reader.read_to_end(&mut data)?;
+        self.put(key, &data, mode)?;
+        Ok(data.len() as u64)
+    }
+
+    fn get(&self, key: &Path) -> Result<Vec<u8>, crate::Error> {
+        match self.files().get(key) {
+            Some(data) => Ok(data.clone()),
+            None => Err(crate::Error::FileNotFound),
+        }
+    }
+
+    fn open(&self, key: &Path) -> Result<std::io::Cursor<Vec<u8>>, crate::Error> {
+        Ok(std::io::Cursor::new(self.get(key)?))
+    }
+
+    fn size(&self, key: &Path) -> Result<u64, crate::Error> {
+        match self.files().get(key) {
+            Some(data) => Ok(data.len() as u64),
+            None => Err(crate::Error::FileNotFound),
+        }
+    }
+
+    fn write_at(&self, key: &Path, offset: u64, data: &[u8]) -> Result<(), crate::Error> {
+        match self.files().get_mut(key) {
+            Some(stored) => {
+                let start = offset as usize;
+                if stored.len() < start + data.len() {
+                    stored.resize(start + data.len(), 0);
+                }
+                stored[start..start + data.len()].copy_from_slice(data);
+                Ok(())
+            }
+            None => Err(crate::Error::FileNotFound),
+        }
+    }
+
+    fn rewrite<F>(&self, key: &Path, write: F) -> Result<(), crate::Error>
+    where
+        F: FnOnce(&mut std::io::Cursor<Vec<u8>>, &mut dyn Write) -> Result<(), std::io::Error>,
+    {
+        let mut source = self.open(key)?;
+        let mut data: Vec<u8> = Vec::new();
+        write(&mut source, &mut data)?;
+        self.files().insert(key.to_path_buf(), data);
+        Ok(())
+    }
+
+    fn delete(&self, key: &Path) -> Result<(), crate::Error> {
+        match self.files().remove(key) {
+            Some(_) => Ok(()),
+            None => Err(crate::Error::FileNotFound),
+        }
+    }
+
+    fn exists(&self, key: &Path) -> Result<bool, crate::Error> {
+        Ok(self.files().contains_key(key))
+    }
+
+    fn list(&self, prefix: &Path) -> Result<Vec<PathBuf>, crate::Error> {
+        Ok(self
+            .files()
+            .keys()
+            .filter(|key| key.starts_with(prefix) && !super::is_hidden(key))
+            .cloned()
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use super::super::Storage;
+    use crate::types::WriteMode;
+
+    #[test]
+    fn test_memory_storage() {
+        let storage = super::MemoryStorage::new();
+        let key = Path::new("music/ab/track.flac");
+
+        storage.put(key, &[1, 2, 3], WriteMode::CreateNew).unwrap();
+        assert!(storage.put(key, &[4], WriteMode::CreateNew).is_err());
+        storage
+            .put(Path::new("musicals/track.flac"), &[5], WriteMode::Overwrite)
+            .unwrap();
+
+        assert_eq!(vec![1, 2, 3], storage.get(key).unwrap());
+        assert_eq!(vec![key], storage.list(Path::new("music")).unwrap());
+
+        storage.delete(key).unwrap();
+        assert!(!storage.exists(key).unwrap());
+        assert!(matches!(
+            storage.delete(key),
+            Err(crate::Error::FileNotFound)
+        ));
+    }
+}
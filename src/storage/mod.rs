@@ -0,0 +1,184 @@
+//! Storage of song and cover art files
+
+use std::io::{Read, Seek, Write};
+use std::path::{Path, PathBuf};
+
+pub mod layout;
+pub mod local;
+pub mod memory;
+
+pub use local::LocalStorage;
+pub use memory::MemoryStorage;
+
+/// A place files of songs and cover art are kept. Keys are the paths of songs and cover art,
+/// such as those returned by `Song::path`
+pub trait Storage {
+    /// Reader over stored data, returned by `open`
+    type Reader: Read + Seek;
+
+    /// Stores data under a key. Depending on the mode, existing data is either replaced or left
+    /// untouched with an error returned
+    fn put(
+        &self,
+        key: &Path,
+        data: &[u8],
+        mode: crate::types::WriteMode,
+    ) -> Result<(), crate::Error>;
+
+    /// Stores data streamed from a reader under a key, with the same modes as `put`. Returns the
+    /// number of bytes stored
+    fn put_from_reader<R: Read>(
+        &self,
+        key: &Path,
+        reader: &mut R,
+        mode: crate::types::WriteMode,
+    ) -> Result<u64, crate::Error>;
+
+    /// Gets the data stored under a key
+    fn get(&self, key: &Path) -> Result<Vec<u8>, crate::Error>;
+
+    /// Opens a reader over the data stored under a key, so it can be streamed rather than loaded
+    /// whole
+    fn open(&self, key: &Path) -> Result<Self::Reader, crate::Error>;
+
+    /// Gets the size in bytes of the data stored under a key
+    fn size(&self, key: &Path) -> Result<u64, crate::Error>;
+
+    /// Overwrites part of the data stored under a key in place, starting at `offset`. Meant for
+    /// changes that keep the length of the data, such as a metadata header that still fits
+    fn write_at(&self, key: &Path, offset: u64, data: &[u8]) -> Result<(), crate::Error>;
+
+    /// Replaces the data stored under a key with the output of `write`, which streams from the
+    /// current data. The current data is left untouched when `write` fails
+    fn rewrite<F>(&self, key: &Path, write: F) -> Result<(), crate::Error>
+    where
+        F: FnOnce(&mut Self::Reader, &mut dyn Write) -> Result<(), std::io::Error>;
+
+    /// Deletes the data stored under a key
+    fn delete(&self, key: &Path) -> Result<(), crate::Error>;
+
+    /// Checks whether data is stored under a key
+    fn exists(&self, key: &Path) -> Result<bool, crate::Error>;
+
+    /// Lists the keys at or under a prefix, sorted. The prefix is matched by whole path
+    /// components, so `music` covers `music/a.flac` but not `musicals/a.flac`. Keys with a
+    /// filename starting with a dot, such as the temporary files of unfinished writes, are left
+    /// out
+    fn list(&self, prefix: &Path) -> Result<Vec<PathBuf>, crate::Error>;
+}
+
+/// Checks whether a key is hidden from listings
+fn is_hidden(key: &Path) -> bool {
+    key.file_name()
+        .is_some_and(|filename| filename.to_string_lossy().starts_with('.'))
+}
+
+/// Collects the files under a directory. With a depth, only files that many directories down are
+/// collected. Temporary files of unfinished writes are skipped
+fn collect_files(
+    directory: &Path,
+    depth: Option<usize>,
+    paths: &mut Vec<PathBuf>,
+) -> Result<(), crate::Error> {
+    for entry in std::fs::read_dir(directory)? {
+        let entry = entry?;
+        let file_type = entry.file_type()?;
+
+        if file_type.is_dir() {
+            match depth {
+                Some(0) => {}
+                Some(depth) => collect_files(&entry.path(), Some(depth - 1), paths)?,
+                None => collect_files(&entry.path(), None, paths)?,
+            }
+        } else if file_type.is_file()
+            && matches!(depth, Some(0) | None)
+            && !is_hidden(&entry.path())
+        {
+            paths.push(entry.path());
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Read, Seek, SeekFrom};
+    use std::path::Path;
+
+    use super::Storage;
+    use crate::types::WriteMode;
+
+    /// Runs the same checks against a backend, with keys under `root`
+    fn check_storage<S: Storage>(storage: &S, root: &Path) {
+        let key = root.join("music/ab/track.flac");
+
+        storage.put(&key, &[1, 2, 3], WriteMode::CreateNew).unwrap();
+        assert!(matches!(
+            storage.put(&key, &[4], WriteMode::CreateNew),
+            Err(crate::Error::FileAlreadyExists)
+        ));
+        assert_eq!(vec![1, 2, 3], storage.get(&key).unwrap());
+        assert_eq!(3, storage.size(&key).unwrap());
+
+        let other = root.join("musicals/track.flac");
+        let written = storage
+            .put_from_reader(&other, &mut &[5, 6][..], WriteMode::Overwrite)
+            .unwrap();
+        assert_eq!(2, written);
+        storage
+            .put(&root.join("music/.track.tmp"), &[7], WriteMode::Overwrite)
+            .unwrap();
+
+        assert_eq!(
+            vec![key.clone()],
+            storage.list(&root.join("music")).unwrap()
+        );
+        assert_eq!(vec![key.clone()], storage.list(&key).unwrap());
+        assert_eq!(
+            vec![key.clone(), other.clone()],
+            storage.list(root).unwrap()
+        );
+        assert!(storage.list(&root.join("missing")).unwrap().is_empty());
+
+        let mut reader = storage.open(&key).unwrap();
+        reader.seek(SeekFrom::Start(1)).unwrap();
+        let mut rest: Vec<u8> = Vec::new();
+        reader.read_to_end(&mut rest).unwrap();
+        assert_eq!(vec![2, 3], rest);
+
+        storage.write_at(&key, 1, &[9]).unwrap();
+        assert_eq!(vec![1, 9, 3], storage.get(&key).unwrap());
+
+        storage
+            .rewrite(&key, |source, writer| {
+                writer.write_all(&[0])?;
+                std::io::copy(source, writer)?;
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(vec![0, 1, 9, 3], storage.get(&key).unwrap());
+        assert!(
+            storage
+                .rewrite(&key, |_, _| Err(std::io::Error::other("failed")))
+                .is_err()
+        );
+        assert_eq!(vec![0, 1, 9, 3], storage.get(&key).unwrap());
+
+        storage.delete(&key).unwrap();
+        assert!(!storage.exists(&key).unwrap());
+        assert!(storage.get(&key).is_err());
+        assert!(storage.open(&key).is_err());
+        assert!(storage.delete(&key).is_err());
+    }
+
+    #[test]
+    fn test_storage_backends() {
+        // Unlike keys, directories have to exist before files are stored in them
+        let temp_dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir_all(temp_dir.path().join("music/ab")).unwrap();
+        std::fs::create_dir(temp_dir.path().join("musicals")).unwrap();
+        check_storage(&super::LocalStorage, temp_dir.path());
+        check_storage(&super::MemoryStorage::new(), Path::new("/library"));
+    }
+}
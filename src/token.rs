@@ -25,6 +25,36 @@ pub struct AccessToken {
     pub expiration: i64,
     #[serde(alias = "message")]
     pub message: String,
+    /// Opaque token exchanged for a new access token once this one expires
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub refresh_token: String,
+}
+
+/// Refresh token as it is kept at rest. Only the hash of the opaque value handed to the client
+/// is stored. Every rotation issues a new token in the same family, so reuse of a rotated token
+/// can revoke the whole family
+#[derive(Clone, Debug, Default, Deserialize, serde::Serialize)]
+pub struct RefreshToken {
+    pub id: uuid::Uuid,
+    pub user_id: uuid::Uuid,
+    pub family_id: uuid::Uuid,
+    /// SHA-256 of the opaque value, hex encoded
+    pub token_hash: String,
+    pub issued: i64,
+    pub expiration: i64,
+    pub revoked: bool,
+    /// Id of the token this one was rotated into. Nil while it is unused
+    #[serde(default, skip_serializing_if = "crate::init::is_uuid_nil")]
+    pub replaced_by: uuid::Uuid,
+}
+
+/// Access and refresh token handed to the client at login or on refresh
+#[derive(Clone, Debug, Default, Deserialize, serde::Serialize)]
+pub struct TokenPair {
+    pub access_token: String,
+    pub expiration: i64,
+    pub refresh_token: String,
+    pub refresh_expiration: i64,
 }
 
 #[derive(Clone, Debug, serde::Serialize, Deserialize)]
@@ -36,6 +66,16 @@ pub struct UserClaims {
     pub exp: i64, // Expiration time (UTC timestamp)
     #[serde(deserialize_with = "deserialize_i64_from_f64")]
     pub iat: i64, // Issued at (UTC timestamp)
+    #[serde(
+        default,
+        skip_serializing_if = "Option::is_none",
+        deserialize_with = "deserialize_option_i64_from_f64"
+    )]
+    pub nbf: Option<i64>, // Not before (UTC timestamp)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<uuid::Uuid>, // User ID, set by create_token when the resource has one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub jti: Option<String>, // Token ID, used to revoke the token
     // pub azp: String,
     // pub gty: String,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -55,9 +95,66 @@ impl AccessToken {
     }
 }
 
+/// Number of characters of the opaque refresh token value
+pub const REFRESH_TOKEN_LENGTH: usize = 64;
+
+impl RefreshToken {
+    /// Creates a refresh token starting a new family, returning the opaque value for the
+    /// client along with the record to store
+    pub fn generate(user_id: uuid::Uuid, duration: time::Duration) -> (String, RefreshToken) {
+        RefreshToken::generate_in_family(user_id, uuid::Uuid::new_v4(), duration)
+    }
+
+    fn generate_in_family(
+        user_id: uuid::Uuid,
+        family_id: uuid::Uuid,
+        duration: time::Duration,
+    ) -> (String, RefreshToken) {
+        use rand::Rng;
+
+        let value: String = rand::rng()
+            .sample_iter(rand::distr::Alphanumeric)
+            .take(REFRESH_TOKEN_LENGTH)
+            .map(char::from)
+            .collect();
+        let issued = time::OffsetDateTime::now_utc();
+        let refresh_token = RefreshToken {
+            id: uuid::Uuid::new_v4(),
+            user_id,
+            family_id,
+            token_hash: RefreshToken::hash(&value),
+            issued: (issued - time::OffsetDateTime::UNIX_EPOCH).whole_seconds(),
+            expiration: (issued + duration - time::OffsetDateTime::UNIX_EPOCH).whole_seconds(),
+            revoked: false,
+            replaced_by: uuid::Uuid::nil(),
+        };
+
+        (value, refresh_token)
+    }
+
+    /// Hashes an opaque refresh token value, for looking up its stored record
+    pub fn hash(value: &str) -> String {
+        crate::util::checksum::sha256(value.as_bytes())
+    }
+
+    /// Checks whether the opaque value belongs to this record
+    pub fn matches(&self, value: &str) -> bool {
+        self.token_hash == RefreshToken::hash(value)
+    }
+
+    pub fn token_expired(&self) -> bool {
+        time::OffsetDateTime::now_utc().unix_timestamp() > self.expiration
+    }
+
+    /// Checks whether the token was rotated or revoked, after which it can not be used again
+    pub fn is_used(&self) -> bool {
+        self.revoked || !self.replaced_by.is_nil()
+    }
+}
+
 impl Token {
-    pub fn _to_json(&self) -> Result<String, serde_json::Error> {
-        serde_json::to_string_pretty(&self)
+    pub fn _to_json(&self) -> Result<String, crate::Error> {
+        Ok(serde_json::to_string_pretty(&self)?)
     }
 
     pub fn token_expired(&self) -> bool {
@@ -90,16 +187,30 @@ where
     }
 }
 
+fn deserialize_option_i64_from_f64<'de, D>(deserializer: D) -> Result<Option<i64>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    struct Wrapper(#[serde(deserialize_with = "deserialize_i64_from_f64")] i64);
+
+    let wrapper = Option::<Wrapper>::deserialize(deserializer)?;
+    Ok(wrapper.map(|Wrapper(val)| val))
+}
+
 pub fn get_issued() -> time::Result<time::OffsetDateTime> {
     Ok(time::OffsetDateTime::now_utc())
 }
 
 mod util {
-    pub fn time_to_std_time(
-        provided_time: &time::OffsetDateTime,
-    ) -> Result<std::time::SystemTime, std::time::SystemTimeError> {
-        let converted = std::time::SystemTime::from(*provided_time);
-        Ok(converted)
+    pub fn time_to_std_time(provided_time: &time::OffsetDateTime) -> std::time::SystemTime {
+        std::time::SystemTime::from(*provided_time)
+    }
+
+    /// Gets the seconds since the unix epoch, negative for times before it
+    pub fn std_time_to_unix(provided_time: &std::time::SystemTime) -> i64 {
+        (time::OffsetDateTime::from(*provided_time) - time::OffsetDateTime::UNIX_EPOCH)
+            .whole_seconds()
     }
 }
 
@@ -113,13 +224,55 @@ pub struct TokenResource {
 
 pub const TOKEN_TYPE: &str = "JWT";
 
+/// Creates a HS256 token signed with the shared secret `key`
 pub fn create_token(
     key: &String,
     token_resource: &TokenResource,
     duration: time::Duration,
-) -> Result<(String, i64), josekit::JoseError> {
+) -> Result<(String, i64), crate::Error> {
+    let signing_key = key::SigningKey::from_secret(key.as_bytes())?;
+    create_token_with_key(&signing_key, token_resource, duration)
+}
+
+/// Creates a token signed with the algorithm of `key`
+pub fn create_token_with_key(
+    key: &key::SigningKey,
+    token_resource: &TokenResource,
+    duration: time::Duration,
+) -> Result<(String, i64), crate::Error> {
+    sign_token(key, None, token_resource, duration)
+}
+
+/// Creates a token signed with the current signing key of `key_set`, stamping its id into the
+/// `kid` header so verifiers can pick the matching key
+pub fn create_token_with_key_set(
+    key_set: &key::KeySet,
+    token_resource: &TokenResource,
+    duration: time::Duration,
+) -> Result<(String, i64), crate::Error> {
+    let now = (time::OffsetDateTime::now_utc() - time::OffsetDateTime::UNIX_EPOCH).whole_seconds();
+    let entry = match key_set.signing_entry(now) {
+        Some(entry) => entry,
+        None => return Err(crate::Error::NoSigningKey),
+    };
+
+    match &entry.signing_key {
+        Some(signing_key) => sign_token(signing_key, Some(&entry.kid), token_resource, duration),
+        None => Err(crate::Error::NoSigningKey),
+    }
+}
+
+fn sign_token(
+    key: &key::SigningKey,
+    key_id: Option<&str>,
+    token_resource: &TokenResource,
+    duration: time::Duration,
+) -> Result<(String, i64), crate::Error> {
     let mut header = josekit::jws::JwsHeader::new();
     header.set_token_type(TOKEN_TYPE);
+    if let Some(key_id) = key_id {
+        header.set_key_id(key_id);
+    }
 
     let mut payload = josekit::jwt::JwtPayload::new();
     let message = &token_resource.message;
@@ -128,29 +281,941 @@ pub fn create_token(
     payload.set_subject(message);
     payload.set_issuer(issuer);
     payload.set_audience(audiences.clone());
+    payload.set_jwt_id(uuid::Uuid::new_v4().to_string());
     if !token_resource.id.is_nil() {
-        match payload.set_claim("id", Some(serde_json::json!(token_resource.id))) {
-            Ok(_) => {}
-            Err(err) => {
-                return Err(err);
-            }
-        }
+        payload.set_claim("id", Some(serde_json::json!(token_resource.id)))?;
     }
     match get_issued() {
         Ok(issued) => {
             let expire = issued + duration;
-            payload.set_issued_at(&util::time_to_std_time(&issued).unwrap());
-            payload.set_expires_at(&util::time_to_std_time(&expire).unwrap());
+            payload.set_issued_at(&util::time_to_std_time(&issued));
+            payload.set_expires_at(&util::time_to_std_time(&expire));
 
-            let signer = josekit::jws::alg::hmac::HmacJwsAlgorithm::Hs256
-                .signer_from_bytes(key.as_bytes())
-                .unwrap();
             Ok((
-                josekit::jwt::encode_with_signer(&payload, &header, &signer).unwrap(),
+                josekit::jwt::encode_with_signer(&payload, &header, key.signer())?,
                 (expire - time::OffsetDateTime::UNIX_EPOCH).whole_seconds(),
             ))
         }
-        Err(e) => Err(josekit::JoseError::InvalidClaim(e.into())),
+        Err(e) => Err(crate::Error::Jose(josekit::JoseError::InvalidClaim(
+            e.into(),
+        ))),
+    }
+}
+
+/// Issues an access token signed by `key_set` together with a refresh token starting a new
+/// family for the user of `token_resource`. The returned RefreshToken is the record to store
+pub fn issue_token_pair(
+    key_set: &key::KeySet,
+    token_resource: &TokenResource,
+    access_duration: time::Duration,
+    refresh_duration: time::Duration,
+) -> Result<(TokenPair, RefreshToken), crate::Error> {
+    if token_resource.id.is_nil() {
+        return Err(crate::Error::IdNotInitialized);
+    }
+
+    let (access_token, expiration) =
+        create_token_with_key_set(key_set, token_resource, access_duration)?;
+    let (refresh_token, record) = RefreshToken::generate(token_resource.id, refresh_duration);
+
+    Ok((
+        TokenPair {
+            access_token,
+            expiration,
+            refresh_expiration: record.expiration,
+            refresh_token,
+        },
+        record,
+    ))
+}
+
+/// Exchanges the refresh token `presented` by the client for a new token pair. `family` holds
+/// the stored tokens of the family the presented token belongs to, found through
+/// `RefreshToken::hash`. The presented token is marked as replaced by the returned record.
+///
+/// Presenting a token that was already rotated or revoked means it leaked, so every token of
+/// the family is revoked and `Error::RefreshTokenReused` is returned. Tokens of other families
+/// passed in by mistake are left untouched. The caller should store the changes made to
+/// `family` in either case
+pub fn refresh_token_pair(
+    key_set: &key::KeySet,
+    family: &mut [RefreshToken],
+    presented: &str,
+    token_resource: &TokenResource,
+    access_duration: time::Duration,
+    refresh_duration: time::Duration,
+) -> Result<(TokenPair, RefreshToken), crate::Error> {
+    let current = match family.iter().position(|token| token.matches(presented)) {
+        Some(index) => index,
+        None => return Err(crate::Error::InvalidToken),
+    };
+
+    if family[current].is_used() {
+        let family_id = family[current].family_id;
+        for token in family
+            .iter_mut()
+            .filter(|token| token.family_id == family_id)
+        {
+            token.revoked = true;
+        }
+        return Err(crate::Error::RefreshTokenReused);
+    } else if family[current].token_expired() {
+        return Err(crate::Error::TokenExpired);
+    } else if family[current].user_id != token_resource.id {
+        return Err(crate::Error::InvalidToken);
+    }
+
+    let (access_token, expiration) =
+        create_token_with_key_set(key_set, token_resource, access_duration)?;
+    let (refresh_token, record) = RefreshToken::generate_in_family(
+        family[current].user_id,
+        family[current].family_id,
+        refresh_duration,
+    );
+    family[current].replaced_by = record.id;
+
+    Ok((
+        TokenPair {
+            access_token,
+            expiration,
+            refresh_expiration: record.expiration,
+            refresh_token,
+        },
+        record,
+    ))
+}
+
+/// Clock skew in seconds tolerated when checking the times of a token
+pub const LEEWAY_SECONDS: i64 = 60;
+
+/// Verifies a HS256 token created by `create_token` and decodes its claims. The signature,
+/// expiration, not before and issued at times, issuer and audience are checked, allowing
+/// `LEEWAY_SECONDS` of clock skew
+pub fn verify_token(
+    key: &str,
+    token: &str,
+    expected_issuer: &str,
+    expected_audience: &str,
+) -> Result<UserClaims, crate::Error> {
+    let verifying_key = key::VerifyingKey::from_secret(key.as_bytes())?;
+    verify_token_with_key(&verifying_key, token, expected_issuer, expected_audience)
+}
+
+/// Verifies a token signed with the algorithm of `key` and decodes its claims, checking the
+/// same claims as `verify_token`. Tokens signed with any other algorithm are rejected
+pub fn verify_token_with_key(
+    key: &key::VerifyingKey,
+    token: &str,
+    expected_issuer: &str,
+    expected_audience: &str,
+) -> Result<UserClaims, crate::Error> {
+    let now = (time::OffsetDateTime::now_utc() - time::OffsetDateTime::UNIX_EPOCH).whole_seconds();
+    verify_token_at(key, token, expected_issuer, expected_audience, now)
+}
+
+fn verify_token_at(
+    key: &key::VerifyingKey,
+    token: &str,
+    expected_issuer: &str,
+    expected_audience: &str,
+    now: i64,
+) -> Result<UserClaims, crate::Error> {
+    let payload = match josekit::jwt::decode_with_verifier(token, key.verifier()) {
+        Ok((payload, _)) => payload,
+        Err(_) => return Err(crate::Error::InvalidToken),
+    };
+
+    claims_from_payload(&payload, expected_issuer, expected_audience, now)
+}
+
+/// Verifies a token created by `create_token_with_key_set`, selecting the key named by its
+/// `kid` header. Besides the checks of `verify_token`, the token must have been issued within
+/// the validity window of that key
+pub fn verify_token_with_key_set(
+    key_set: &key::KeySet,
+    token: &str,
+    expected_issuer: &str,
+    expected_audience: &str,
+) -> Result<UserClaims, crate::Error> {
+    let now = (time::OffsetDateTime::now_utc() - time::OffsetDateTime::UNIX_EPOCH).whole_seconds();
+    verify_token_with_key_set_at(key_set, token, expected_issuer, expected_audience, now)
+}
+
+fn verify_token_with_key_set_at(
+    key_set: &key::KeySet,
+    token: &str,
+    expected_issuer: &str,
+    expected_audience: &str,
+    now: i64,
+) -> Result<UserClaims, crate::Error> {
+    let header = match josekit::jwt::decode_header(token) {
+        Ok(header) => header,
+        Err(_) => return Err(crate::Error::InvalidToken),
+    };
+    let entry = match header
+        .claim("kid")
+        .and_then(|kid| kid.as_str())
+        .and_then(|kid| key_set.get(kid))
+    {
+        Some(entry) => entry,
+        None => return Err(crate::Error::UnknownKeyId),
+    };
+
+    let claims = verify_token_at(
+        &entry.verifying_key,
+        token,
+        expected_issuer,
+        expected_audience,
+        now,
+    )?;
+    if entry.contains(claims.iat) {
+        Ok(claims)
+    } else {
+        Err(crate::Error::InvalidToken)
+    }
+}
+
+/// Checks the registered claims of a verified payload and decodes them
+fn claims_from_payload(
+    payload: &josekit::jwt::JwtPayload,
+    expected_issuer: &str,
+    expected_audience: &str,
+    now: i64,
+) -> Result<UserClaims, crate::Error> {
+    let exp = match payload.expires_at() {
+        Some(exp) => util::std_time_to_unix(&exp),
+        None => return Err(crate::Error::InvalidToken),
+    };
+    let iat = match payload.issued_at() {
+        Some(iat) => util::std_time_to_unix(&iat),
+        None => return Err(crate::Error::InvalidToken),
+    };
+    let nbf = payload.not_before().map(|nbf| util::std_time_to_unix(&nbf));
+
+    if now > exp + LEEWAY_SECONDS {
+        return Err(crate::Error::TokenExpired);
+    } else if iat > now + LEEWAY_SECONDS || nbf.is_some_and(|nbf| nbf > now + LEEWAY_SECONDS) {
+        return Err(crate::Error::TokenNotYetValid);
+    }
+
+    if payload.issuer() != Some(expected_issuer) {
+        return Err(crate::Error::InvalidTokenIssuer);
+    }
+    match payload.audience() {
+        Some(audiences) if audiences.contains(&expected_audience) => {}
+        _ => return Err(crate::Error::InvalidTokenAudience),
+    }
+
+    let id = match payload.claim("id") {
+        Some(id) => match serde_json::from_value::<uuid::Uuid>(id.clone()) {
+            Ok(id) => Some(id),
+            Err(_) => return Err(crate::Error::InvalidToken),
+        },
+        None => None,
+    };
+    let roles = match payload.claim("roles") {
+        Some(roles) => match serde_json::from_value::<Vec<String>>(roles.clone()) {
+            Ok(roles) => Some(roles),
+            Err(_) => return Err(crate::Error::InvalidToken),
+        },
+        None => None,
+    };
+
+    Ok(UserClaims {
+        iss: String::from(expected_issuer),
+        aud: String::from(expected_audience),
+        sub: String::from(payload.subject().unwrap_or_default()),
+        exp,
+        iat,
+        nbf,
+        id,
+        jti: payload.jwt_id().map(String::from),
+        roles,
+    })
+}
+
+/// Verifies a token like `verify_token_with_key_set` and rejects it when its `jti` has been
+/// revoked in `store`. Tokens without a `jti`, created before it was added, can not be revoked
+/// and are accepted until they expire
+pub fn verify_token_with_revocation<R: revocation::RevocationStore + ?Sized>(
+    key_set: &key::KeySet,
+    store: &R,
+    token: &str,
+    expected_issuer: &str,
+    expected_audience: &str,
+) -> Result<UserClaims, crate::Error> {
+    let claims = verify_token_with_key_set(key_set, token, expected_issuer, expected_audience)?;
+    revocation::check(store, claims)
+}
+
+/// Verifies a HS256 token like `verify_token` and rejects it when its `jti` has been revoked in
+/// `store`
+pub fn verify_token_with_store<R: revocation::RevocationStore + ?Sized>(
+    key: &str,
+    store: &R,
+    token: &str,
+    expected_issuer: &str,
+    expected_audience: &str,
+) -> Result<UserClaims, crate::Error> {
+    let claims = verify_token(key, token, expected_issuer, expected_audience)?;
+    revocation::check(store, claims)
+}
+
+/// Verifies a token like `verify_token_with_key` and rejects it when its `jti` has been revoked
+/// in `store`
+pub fn verify_token_with_key_and_store<R: revocation::RevocationStore + ?Sized>(
+    key: &key::VerifyingKey,
+    store: &R,
+    token: &str,
+    expected_issuer: &str,
+    expected_audience: &str,
+) -> Result<UserClaims, crate::Error> {
+    let claims = verify_token_with_key(key, token, expected_issuer, expected_audience)?;
+    revocation::check(store, claims)
+}
+
+/// Revocation of tokens before they expire, tracked by their `jti`
+pub mod revocation {
+    use std::collections::BTreeMap;
+    use std::path::{Path, PathBuf};
+    use std::sync::Mutex;
+
+    /// Keeps the ids of revoked tokens until the tokens expire
+    pub trait RevocationStore {
+        /// Revokes the token with the id `jti`, which expires at the unix timestamp `expiration`
+        fn revoke(&self, jti: &str, expiration: i64) -> Result<(), crate::Error>;
+
+        fn is_revoked(&self, jti: &str) -> Result<bool, crate::Error>;
+
+        /// Removes the entries of tokens that expired before `now`, since verification rejects
+        /// them anyway. Returns the number of entries removed
+        fn prune(&self, now: i64) -> Result<usize, crate::Error>;
+    }
+
+    /// Revokes the token the claims were decoded from
+    pub fn revoke<R: RevocationStore + ?Sized>(
+        store: &R,
+        claims: &super::UserClaims,
+    ) -> Result<(), crate::Error> {
+        match &claims.jti {
+            Some(jti) => store.revoke(jti, claims.exp),
+            None => Err(crate::Error::IdNotInitialized),
+        }
+    }
+
+    /// Passes verified claims through unless their `jti` has been revoked
+    pub(super) fn check<R: RevocationStore + ?Sized>(
+        store: &R,
+        claims: super::UserClaims,
+    ) -> Result<super::UserClaims, crate::Error> {
+        match &claims.jti {
+            Some(jti) if store.is_revoked(jti)? => Err(crate::Error::TokenRevoked),
+            _ => Ok(claims),
+        }
+    }
+
+    /// Removes expired entries, keeping them for `LEEWAY_SECONDS` past their expiration like
+    /// verification does
+    fn prune_entries(entries: &mut BTreeMap<String, i64>, now: i64) -> usize {
+        let count = entries.len();
+        entries.retain(|_, expiration| *expiration + super::LEEWAY_SECONDS >= now);
+        count - entries.len()
+    }
+
+    /// Keeps revoked tokens in memory, for a single process or tests
+    #[derive(Debug, Default)]
+    pub struct MemoryRevocationStore {
+        entries: Mutex<BTreeMap<String, i64>>,
+    }
+
+    impl MemoryRevocationStore {
+        pub fn new() -> MemoryRevocationStore {
+            MemoryRevocationStore::default()
+        }
+    }
+
+    impl RevocationStore for MemoryRevocationStore {
+        fn revoke(&self, jti: &str, expiration: i64) -> Result<(), crate::Error> {
+            crate::util::sync::lock(&self.entries).insert(String::from(jti), expiration);
+            Ok(())
+        }
+
+        fn is_revoked(&self, jti: &str) -> Result<bool, crate::Error> {
+            Ok(crate::util::sync::lock(&self.entries).contains_key(jti))
+        }
+
+        fn prune(&self, now: i64) -> Result<usize, crate::Error> {
+            Ok(prune_entries(
+                &mut crate::util::sync::lock(&self.entries),
+                now,
+            ))
+        }
+    }
+
+    /// Keeps revoked tokens in a JSON file mapping each `jti` to its expiration. The file can be
+    /// shared by several processes: it is read on every check, and every change is made while
+    /// holding an advisory lock on a `.lock` file next to it, so concurrent changes are merged
+    /// rather than lost. The lock file is left in place
+    #[derive(Debug)]
+    pub struct FileRevocationStore {
+        path: PathBuf,
+        lock_path: PathBuf,
+    }
+
+    impl FileRevocationStore {
+        /// Opens the store kept at `path`, starting empty when the file does not exist yet
+        pub fn open(path: &Path) -> Result<FileRevocationStore, crate::Error> {
+            let filename = match path.file_name() {
+                Some(filename) => filename.to_string_lossy(),
+                None => return Err(crate::Error::FilenameNotInitialized),
+            };
+            FileRevocationStore::load(path)?;
+
+            Ok(FileRevocationStore {
+                path: path.to_path_buf(),
+                lock_path: path.with_file_name(format!("{filename}.lock")),
+            })
+        }
+
+        pub fn path(&self) -> &Path {
+            &self.path
+        }
+
+        fn load(path: &Path) -> Result<BTreeMap<String, i64>, crate::Error> {
+            match std::fs::read(path) {
+                Ok(data) => Ok(serde_json::from_slice(&data)?),
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+                Err(err) => Err(crate::Error::Io(err)),
+            }
+        }
+
+        /// Applies a change to the latest entries in the file and writes them back, holding the
+        /// lock from the read until the new file is in place
+        fn update<T, F>(&self, change: F) -> Result<T, crate::Error>
+        where
+            F: FnOnce(&mut BTreeMap<String, i64>) -> (T, bool),
+        {
+            let lock = std::fs::OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create(true)
+                .truncate(false)
+                .open(&self.lock_path)?;
+            lock.lock()?;
+
+            let mut entries = FileRevocationStore::load(&self.path)?;
+            let (value, changed) = change(&mut entries);
+            if changed {
+                let data = serde_json::to_vec_pretty(&entries)?;
+                crate::util::atomic::write(&self.path, &data, crate::types::WriteMode::Overwrite)?;
+            }
+            // Closing the lock file releases the lock
+            Ok(value)
+        }
+    }
+
+    impl RevocationStore for FileRevocationStore {
+        fn revoke(&self, jti: &str, expiration: i64) -> Result<(), crate::Error> {
+            self.update(|entries| {
+                entries.insert(String::from(jti), expiration);
+                ((), true)
+            })
+        }
+
+        fn is_revoked(&self, jti: &str) -> Result<bool, crate::Error> {
+            // The file is replaced by renaming, so it is always read whole without the lock
+            Ok(FileRevocationStore::load(&self.path)?.contains_key(jti))
+        }
+
+        fn prune(&self, now: i64) -> Result<usize, crate::Error> {
+            self.update(|entries| {
+                let removed = prune_entries(entries, now);
+                (removed, removed > 0)
+            })
+        }
+    }
+}
+
+/// Keys for signing and verifying tokens. HS256 shares one secret between the service that
+/// creates tokens and those that verify them, while RS256, ES256 and EdDSA keys let verifiers
+/// hold only the public key
+pub mod key {
+    use josekit::jwk::KeyPair;
+    use josekit::jws::alg::ecdsa::EcdsaJwsAlgorithm;
+    use josekit::jws::alg::eddsa::EddsaJwsAlgorithm;
+    use josekit::jws::alg::hmac::HmacJwsAlgorithm;
+    use josekit::jws::alg::rsassa::RsassaJwsAlgorithm;
+    use josekit::jws::{JwsSigner, JwsVerifier};
+
+    #[derive(Clone, Copy, Debug, PartialEq)]
+    pub enum Algorithm {
+        /// HMAC with SHA-256 using a shared secret
+        Hs256,
+        /// RSASSA-PKCS1-v1_5 with SHA-256
+        Rs256,
+        /// ECDSA with the P-256 curve and SHA-256
+        Es256,
+        /// EdDSA, usually with the Ed25519 curve
+        EdDsa,
+    }
+
+    impl Algorithm {
+        /// Gets the name used in the `alg` header of a token
+        pub fn name(&self) -> &'static str {
+            match self {
+                Algorithm::Hs256 => "HS256",
+                Algorithm::Rs256 => "RS256",
+                Algorithm::Es256 => "ES256",
+                Algorithm::EdDsa => "EdDSA",
+            }
+        }
+
+        /// Gets the Algorithm of an `alg` header value such as "ES256"
+        pub fn from_name(name: &str) -> Option<Algorithm> {
+            [
+                Algorithm::Hs256,
+                Algorithm::Rs256,
+                Algorithm::Es256,
+                Algorithm::EdDsa,
+            ]
+            .into_iter()
+            .find(|algorithm| algorithm.name() == name)
+        }
+
+        /// Guesses the Algorithm of a JWK without an `alg` parameter from its key type
+        fn from_jwk(jwk: &josekit::jwk::Jwk) -> Option<Algorithm> {
+            match jwk.algorithm() {
+                Some(name) => Algorithm::from_name(name),
+                None => match (jwk.key_type(), jwk.curve()) {
+                    ("RSA", _) => Some(Algorithm::Rs256),
+                    ("EC", Some("P-256")) => Some(Algorithm::Es256),
+                    ("OKP", Some("Ed25519" | "Ed448")) => Some(Algorithm::EdDsa),
+                    _ => None,
+                },
+            }
+        }
+    }
+
+    /// Members of RSA, EC and OKP JWKs that make up the public key
+    const PUBLIC_JWK_MEMBERS: [&str; 5] = ["n", "e", "crv", "x", "y"];
+
+    enum Source<'a> {
+        Pem(&'a [u8]),
+        Der(&'a [u8]),
+        Jwk(&'a josekit::jwk::Jwk),
+    }
+
+    /// Private key, or shared secret, used to create tokens
+    #[derive(Debug)]
+    pub struct SigningKey {
+        algorithm: Algorithm,
+        signer: Box<dyn JwsSigner>,
+        verifying_key: VerifyingKey,
+    }
+
+    /// Public key, or shared secret, used to verify tokens
+    #[derive(Debug)]
+    pub struct VerifyingKey {
+        algorithm: Algorithm,
+        verifier: Box<dyn JwsVerifier>,
+        /// Public JWK of asymmetric keys, when it is known
+        jwk: Option<josekit::jwk::Jwk>,
+    }
+
+    impl Clone for SigningKey {
+        fn clone(&self) -> Self {
+            SigningKey {
+                algorithm: self.algorithm,
+                signer: self.signer.box_clone(),
+                verifying_key: self.verifying_key.clone(),
+            }
+        }
+    }
+
+    impl Clone for VerifyingKey {
+        fn clone(&self) -> Self {
+            VerifyingKey {
+                algorithm: self.algorithm,
+                verifier: self.verifier.box_clone(),
+                jwk: self.jwk.clone(),
+            }
+        }
+    }
+
+    impl SigningKey {
+        /// Creates a HS256 key from a shared secret
+        pub fn from_secret(secret: impl AsRef<[u8]>) -> Result<SigningKey, crate::Error> {
+            Ok(SigningKey {
+                algorithm: Algorithm::Hs256,
+                signer: Box::new(HmacJwsAlgorithm::Hs256.signer_from_bytes(&secret)?),
+                verifying_key: VerifyingKey::from_secret(&secret)?,
+            })
+        }
+
+        /// Creates a key from a PEM encoded private key. HS256 keys have no PEM encoding, use
+        /// `from_secret` for them
+        pub fn from_pem(
+            algorithm: Algorithm,
+            input: impl AsRef<[u8]>,
+        ) -> Result<SigningKey, crate::Error> {
+            SigningKey::from_key_pair(
+                algorithm,
+                load_key_pair(algorithm, Source::Pem(input.as_ref()))?,
+            )
+        }
+
+        /// Creates a key from a DER encoded private key
+        pub fn from_der(
+            algorithm: Algorithm,
+            input: impl AsRef<[u8]>,
+        ) -> Result<SigningKey, crate::Error> {
+            SigningKey::from_key_pair(
+                algorithm,
+                load_key_pair(algorithm, Source::Der(input.as_ref()))?,
+            )
+        }
+
+        /// Creates a key from a private JWK, or a symmetric JWK for HS256
+        pub fn from_jwk(
+            algorithm: Algorithm,
+            input: impl AsRef<[u8]>,
+        ) -> Result<SigningKey, crate::Error> {
+            let jwk = josekit::jwk::Jwk::from_bytes(input)?;
+            match algorithm {
+                Algorithm::Hs256 => Ok(SigningKey {
+                    algorithm,
+                    signer: Box::new(HmacJwsAlgorithm::Hs256.signer_from_jwk(&jwk)?),
+                    verifying_key: VerifyingKey {
+                        algorithm,
+                        verifier: Box::new(HmacJwsAlgorithm::Hs256.verifier_from_jwk(&jwk)?),
+                        jwk: None,
+                    },
+                }),
+                _ => SigningKey::from_key_pair(
+                    algorithm,
+                    load_key_pair(algorithm, Source::Jwk(&jwk))?,
+                ),
+            }
+        }
+
+        fn from_key_pair(
+            algorithm: Algorithm,
+            key_pair: Box<dyn KeyPair>,
+        ) -> Result<SigningKey, crate::Error> {
+            let private_key = key_pair.to_jwk_private_key();
+            let signer: Box<dyn JwsSigner> = match algorithm {
+                Algorithm::Hs256 => return Err(crate::Error::UnsupportedKeyFormat),
+                Algorithm::Rs256 => {
+                    Box::new(RsassaJwsAlgorithm::Rs256.signer_from_jwk(&private_key)?)
+                }
+                Algorithm::Es256 => {
+                    Box::new(EcdsaJwsAlgorithm::Es256.signer_from_jwk(&private_key)?)
+                }
+                Algorithm::EdDsa => {
+                    Box::new(EddsaJwsAlgorithm::Eddsa.signer_from_jwk(&private_key)?)
+                }
+            };
+
+            Ok(SigningKey {
+                algorithm,
+                signer,
+                verifying_key: VerifyingKey::from_public_jwk(
+                    algorithm,
+                    &key_pair.to_jwk_public_key(),
+                )?,
+            })
+        }
+
+        pub fn algorithm(&self) -> Algorithm {
+            self.algorithm
+        }
+
+        /// Gets the key that verifies tokens created with this key
+        pub fn verifying_key(&self) -> &VerifyingKey {
+            &self.verifying_key
+        }
+
+        pub(crate) fn signer(&self) -> &dyn JwsSigner {
+            self.signer.as_ref()
+        }
+    }
+
+    impl VerifyingKey {
+        /// Creates a HS256 key from a shared secret
+        pub fn from_secret(secret: impl AsRef<[u8]>) -> Result<VerifyingKey, crate::Error> {
+            Ok(VerifyingKey {
+                algorithm: Algorithm::Hs256,
+                verifier: Box::new(HmacJwsAlgorithm::Hs256.verifier_from_bytes(secret)?),
+                jwk: None,
+            })
+        }
+
+        /// Creates a key from a PEM encoded public key
+        pub fn from_pem(
+            algorithm: Algorithm,
+            input: impl AsRef<[u8]>,
+        ) -> Result<VerifyingKey, crate::Error> {
+            let verifier: Box<dyn JwsVerifier> = match algorithm {
+                Algorithm::Hs256 => return Err(crate::Error::UnsupportedKeyFormat),
+                Algorithm::Rs256 => Box::new(RsassaJwsAlgorithm::Rs256.verifier_from_pem(input)?),
+                Algorithm::Es256 => Box::new(EcdsaJwsAlgorithm::Es256.verifier_from_pem(input)?),
+                Algorithm::EdDsa => Box::new(EddsaJwsAlgorithm::Eddsa.verifier_from_pem(input)?),
+            };
+            Ok(VerifyingKey {
+                algorithm,
+                verifier,
+                jwk: None,
+            })
+        }
+
+        /// Creates a key from a DER encoded public key
+        pub fn from_der(
+            algorithm: Algorithm,
+            input: impl AsRef<[u8]>,
+        ) -> Result<VerifyingKey, crate::Error> {
+            let verifier: Box<dyn JwsVerifier> = match algorithm {
+                Algorithm::Hs256 => return Err(crate::Error::UnsupportedKeyFormat),
+                Algorithm::Rs256 => Box::new(RsassaJwsAlgorithm::Rs256.verifier_from_der(input)?),
+                Algorithm::Es256 => Box::new(EcdsaJwsAlgorithm::Es256.verifier_from_der(input)?),
+                Algorithm::EdDsa => Box::new(EddsaJwsAlgorithm::Eddsa.verifier_from_der(input)?),
+            };
+            Ok(VerifyingKey {
+                algorithm,
+                verifier,
+                jwk: None,
+            })
+        }
+
+        /// Creates a key from a public JWK, or a symmetric JWK for HS256. Only the public members
+        /// of a private JWK are kept
+        pub fn from_jwk(
+            algorithm: Algorithm,
+            input: impl AsRef<[u8]>,
+        ) -> Result<VerifyingKey, crate::Error> {
+            VerifyingKey::from_public_jwk(algorithm, &josekit::jwk::Jwk::from_bytes(input)?)
+        }
+
+        fn from_public_jwk(
+            algorithm: Algorithm,
+            jwk: &josekit::jwk::Jwk,
+        ) -> Result<VerifyingKey, crate::Error> {
+            let verifier: Box<dyn JwsVerifier> = match algorithm {
+                Algorithm::Hs256 => Box::new(HmacJwsAlgorithm::Hs256.verifier_from_jwk(jwk)?),
+                Algorithm::Rs256 => Box::new(RsassaJwsAlgorithm::Rs256.verifier_from_jwk(jwk)?),
+                Algorithm::Es256 => Box::new(EcdsaJwsAlgorithm::Es256.verifier_from_jwk(jwk)?),
+                Algorithm::EdDsa => Box::new(EddsaJwsAlgorithm::Eddsa.verifier_from_jwk(jwk)?),
+            };
+            // Secrets are never published, only the public half of asymmetric keys
+            let jwk = match algorithm {
+                Algorithm::Hs256 => None,
+                _ => Some(public_jwk(jwk)?),
+            };
+            Ok(VerifyingKey {
+                algorithm,
+                verifier,
+                jwk,
+            })
+        }
+
+        pub fn algorithm(&self) -> Algorithm {
+            self.algorithm
+        }
+
+        pub(crate) fn verifier(&self) -> &dyn JwsVerifier {
+            self.verifier.as_ref()
+        }
+    }
+
+    /// Key of a KeySet, named by the id stamped into the `kid` header of the tokens it signs
+    #[derive(Clone, Debug)]
+    pub struct KeyEntry {
+        pub kid: String,
+        /// Set for keys that create tokens, verification only keys such as those from a JWKS
+        /// document leave it empty
+        pub signing_key: Option<SigningKey>,
+        pub verifying_key: VerifyingKey,
+        /// Unix timestamp from which the key signs tokens, unbounded when not set
+        pub not_before: Option<i64>,
+        /// Unix timestamp at which the key stops signing tokens, unbounded when not set. Tokens
+        /// it already signed still verify until the key is removed from the KeySet
+        pub not_after: Option<i64>,
+    }
+
+    impl KeyEntry {
+        /// Checks whether a token issued at `issued` falls within the validity window, allowing
+        /// `LEEWAY_SECONDS` of clock skew
+        pub fn contains(&self, issued: i64) -> bool {
+            self.not_before
+                .is_none_or(|not_before| issued >= not_before - super::LEEWAY_SECONDS)
+                && self
+                    .not_after
+                    .is_none_or(|not_after| issued < not_after + super::LEEWAY_SECONDS)
+        }
+
+        fn signs_at(&self, now: i64) -> bool {
+            self.signing_key.is_some()
+                && self.not_before.is_none_or(|not_before| now >= not_before)
+                && self.not_after.is_none_or(|not_after| now < not_after)
+        }
+    }
+
+    /// Keys used to sign and verify tokens, rotated by adding a key that starts signing at a
+    /// later time and removing the old key once the last token it signed has expired
+    #[derive(Clone, Debug, Default)]
+    pub struct KeySet {
+        keys: Vec<KeyEntry>,
+    }
+
+    impl KeySet {
+        pub fn new() -> KeySet {
+            KeySet::default()
+        }
+
+        /// Adds a key that signs tokens between `not_before` and `not_after`
+        pub fn add_signing_key(
+            &mut self,
+            kid: &str,
+            signing_key: SigningKey,
+            not_before: Option<i64>,
+            not_after: Option<i64>,
+        ) -> Result<(), crate::Error> {
+            let verifying_key = signing_key.verifying_key().clone();
+            self.add(KeyEntry {
+                kid: String::from(kid),
+                signing_key: Some(signing_key),
+                verifying_key,
+                not_before,
+                not_after,
+            })
+        }
+
+        /// Adds a key that only verifies tokens issued between `not_before` and `not_after`
+        pub fn add_verifying_key(
+            &mut self,
+            kid: &str,
+            verifying_key: VerifyingKey,
+            not_before: Option<i64>,
+            not_after: Option<i64>,
+        ) -> Result<(), crate::Error> {
+            self.add(KeyEntry {
+                kid: String::from(kid),
+                signing_key: None,
+                verifying_key,
+                not_before,
+                not_after,
+            })
+        }
+
+        fn add(&mut self, entry: KeyEntry) -> Result<(), crate::Error> {
+            if entry.kid.is_empty() {
+                Err(crate::Error::IdNotInitialized)
+            } else if self.get(&entry.kid).is_some() {
+                Err(crate::Error::DuplicateKeyId)
+            } else {
+                self.keys.push(entry);
+                Ok(())
+            }
+        }
+
+        /// Removes a key, returning it when it was part of the set
+        pub fn remove(&mut self, kid: &str) -> Option<KeyEntry> {
+            let index = self.keys.iter().position(|entry| entry.kid == kid)?;
+            Some(self.keys.remove(index))
+        }
+
+        pub fn get(&self, kid: &str) -> Option<&KeyEntry> {
+            self.keys.iter().find(|entry| entry.kid == kid)
+        }
+
+        pub fn keys(&self) -> &[KeyEntry] {
+            &self.keys
+        }
+
+        /// Gets the key that signs tokens at `now`. When the windows of several keys overlap the
+        /// one that started signing last is picked
+        pub fn signing_entry(&self, now: i64) -> Option<&KeyEntry> {
+            self.keys
+                .iter()
+                .filter(|entry| entry.signs_at(now))
+                .max_by_key(|entry| entry.not_before.unwrap_or(i64::MIN))
+        }
+
+        /// Serializes the public keys as a JWKS document. HS256 secrets and public keys loaded
+        /// from PEM or DER are left out, since their JWK is not known
+        pub fn to_jwks(&self) -> String {
+            let mut jwk_set = josekit::jwk::JwkSet::new();
+            for entry in &self.keys {
+                if let Some(jwk) = &entry.verifying_key.jwk {
+                    let mut jwk = jwk.clone();
+                    jwk.set_key_id(entry.kid.clone());
+                    jwk.set_algorithm(entry.verifying_key.algorithm.name());
+                    jwk.set_key_use("sig");
+                    jwk_set.push_key(jwk);
+                }
+            }
+            jwk_set.to_string()
+        }
+
+        /// Creates a KeySet of verification only keys from a JWKS document. Every key needs a
+        /// `kid`, the algorithm is taken from `alg` or guessed from the key type
+        pub fn from_jwks(input: impl AsRef<[u8]>) -> Result<KeySet, crate::Error> {
+            let jwk_set = josekit::jwk::JwkSet::from_bytes(input)?;
+            let mut key_set = KeySet::new();
+            for jwk in jwk_set.keys() {
+                let kid = match jwk.key_id() {
+                    Some(kid) => kid,
+                    None => return Err(crate::Error::IdNotInitialized),
+                };
+                let algorithm = match Algorithm::from_jwk(jwk) {
+                    Some(Algorithm::Hs256) | None => {
+                        return Err(crate::Error::UnsupportedKeyFormat);
+                    }
+                    Some(algorithm) => algorithm,
+                };
+                let verifying_key = VerifyingKey::from_public_jwk(algorithm, jwk)?;
+                key_set.add_verifying_key(kid, verifying_key, None, None)?;
+            }
+            Ok(key_set)
+        }
+    }
+
+    /// Copies only the public members of a JWK, so the private members of a key pair loaded
+    /// from a private JWK can never be published
+    fn public_jwk(jwk: &josekit::jwk::Jwk) -> Result<josekit::jwk::Jwk, crate::Error> {
+        let mut public = josekit::jwk::Jwk::new(jwk.key_type());
+        for name in PUBLIC_JWK_MEMBERS {
+            if let Some(value) = jwk.parameter(name) {
+                public.set_parameter(name, Some(value.clone()))?;
+            }
+        }
+        Ok(public)
+    }
+
+    fn load_key_pair(
+        algorithm: Algorithm,
+        source: Source,
+    ) -> Result<Box<dyn KeyPair>, crate::Error> {
+        use josekit::jwk::alg::ec::{EcCurve, EcKeyPair};
+        use josekit::jwk::alg::ed::EdKeyPair;
+        use josekit::jwk::alg::rsa::RsaKeyPair;
+
+        let key_pair: Box<dyn KeyPair> = match (algorithm, source) {
+            (Algorithm::Hs256, _) => return Err(crate::Error::UnsupportedKeyFormat),
+            (Algorithm::Rs256, Source::Pem(input)) => Box::new(RsaKeyPair::from_pem(input)?),
+            (Algorithm::Rs256, Source::Der(input)) => Box::new(RsaKeyPair::from_der(input)?),
+            (Algorithm::Rs256, Source::Jwk(jwk)) => Box::new(RsaKeyPair::from_jwk(jwk)?),
+            (Algorithm::Es256, Source::Pem(input)) => {
+                Box::new(EcKeyPair::from_pem(input, Some(EcCurve::P256))?)
+            }
+            (Algorithm::Es256, Source::Der(input)) => {
+                Box::new(EcKeyPair::from_der(input, Some(EcCurve::P256))?)
+            }
+            (Algorithm::Es256, Source::Jwk(jwk)) => Box::new(EcKeyPair::from_jwk(jwk)?),
+            (Algorithm::EdDsa, Source::Pem(input)) => Box::new(EdKeyPair::from_pem(input)?),
+            (Algorithm::EdDsa, Source::Der(input)) => Box::new(EdKeyPair::from_der(input)?),
+            (Algorithm::EdDsa, Source::Jwk(jwk)) => Box::new(EdKeyPair::from_jwk(jwk)?),
+        };
+        Ok(key_pair)
     }
 }
 
@@ -226,4 +1291,541 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn test_verify_token() {
+        let key = test_key();
+        let mut test_token_resource = test_resource();
+        test_token_resource.id = uuid::Uuid::new_v4();
+
+        let (token, expire) =
+            create_token(&key, &test_token_resource, time::Duration::hours(2)).unwrap();
+        let claims = verify_token(&key, &token, "icarus_auth_test", "icarus_test").unwrap();
+
+        assert_eq!(test_token_resource.issuer, claims.iss);
+        assert_eq!("icarus_test", claims.aud);
+        assert_eq!(test_token_resource.message, claims.sub);
+        assert_eq!(expire, claims.exp);
+        assert_eq!(Some(test_token_resource.id), claims.id);
+        assert_eq!(None, claims.nbf);
+    }
+
+    #[test]
+    fn test_verify_token_rejects() {
+        let key = test_key();
+        let (token, expire) =
+            create_token(&key, &test_resource(), time::Duration::hours(2)).unwrap();
+
+        assert!(matches!(
+            verify_token(
+                &test_key().to_lowercase(),
+                &token,
+                "icarus_auth_test",
+                "icarus_test"
+            ),
+            Err(crate::Error::InvalidToken)
+        ));
+        assert!(matches!(
+            verify_token(&key, "not.a.token", "icarus_auth_test", "icarus_test"),
+            Err(crate::Error::InvalidToken)
+        ));
+        assert!(matches!(
+            verify_token(&key, &token, "icarus_other", "icarus_test"),
+            Err(crate::Error::InvalidTokenIssuer)
+        ));
+        assert!(matches!(
+            verify_token(&key, &token, "icarus_auth_test", "icarus_other"),
+            Err(crate::Error::InvalidTokenAudience)
+        ));
+
+        let verifying_key = key::VerifyingKey::from_secret(&key).unwrap();
+        let after_expire = expire + LEEWAY_SECONDS + 1;
+        assert!(matches!(
+            verify_token_at(
+                &verifying_key,
+                &token,
+                "icarus_auth_test",
+                "icarus_test",
+                after_expire
+            ),
+            Err(crate::Error::TokenExpired)
+        ));
+        assert!(
+            verify_token_at(
+                &verifying_key,
+                &token,
+                "icarus_auth_test",
+                "icarus_test",
+                expire
+            )
+            .is_ok()
+        );
+
+        let before_issued = expire - time::Duration::hours(2).whole_seconds() - LEEWAY_SECONDS - 1;
+        assert!(matches!(
+            verify_token_at(
+                &verifying_key,
+                &token,
+                "icarus_auth_test",
+                "icarus_test",
+                before_issued
+            ),
+            Err(crate::Error::TokenNotYetValid)
+        ));
+    }
+
+    #[test]
+    fn test_asymmetric_keys() {
+        use josekit::jwk::KeyPair;
+        use josekit::jwk::alg::ec::{EcCurve, EcKeyPair};
+        use josekit::jwk::alg::ed::{EdCurve, EdKeyPair};
+        use josekit::jwk::alg::rsa::RsaKeyPair;
+
+        let key_pairs: Vec<(key::Algorithm, Box<dyn KeyPair>)> = vec![
+            (
+                key::Algorithm::Rs256,
+                Box::new(RsaKeyPair::generate(2048).unwrap()),
+            ),
+            (
+                key::Algorithm::Es256,
+                Box::new(EcKeyPair::generate(EcCurve::P256).unwrap()),
+            ),
+            (
+                key::Algorithm::EdDsa,
+                Box::new(EdKeyPair::generate(EdCurve::Ed25519).unwrap()),
+            ),
+        ];
+
+        for (algorithm, key_pair) in key_pairs {
+            let signing_keys = vec![
+                key::SigningKey::from_pem(algorithm, key_pair.to_pem_private_key()).unwrap(),
+                key::SigningKey::from_der(algorithm, key_pair.to_der_private_key()).unwrap(),
+                key::SigningKey::from_jwk(algorithm, key_pair.to_jwk_private_key().to_string())
+                    .unwrap(),
+            ];
+            let verifying_keys = vec![
+                key::VerifyingKey::from_pem(algorithm, key_pair.to_pem_public_key()).unwrap(),
+                key::VerifyingKey::from_der(algorithm, key_pair.to_der_public_key()).unwrap(),
+                key::VerifyingKey::from_jwk(algorithm, key_pair.to_jwk_public_key().to_string())
+                    .unwrap(),
+                signing_keys[0].verifying_key().clone(),
+            ];
+
+            for signing_key in &signing_keys {
+                assert_eq!(algorithm, signing_key.algorithm());
+                let (token, _) =
+                    create_token_with_key(signing_key, &test_resource(), time::Duration::hours(2))
+                        .unwrap();
+
+                for verifying_key in &verifying_keys {
+                    let claims = verify_token_with_key(
+                        verifying_key,
+                        &token,
+                        "icarus_auth_test",
+                        "icarus_test",
+                    )
+                    .unwrap();
+                    assert_eq!("Authorization", claims.sub);
+                }
+
+                assert!(matches!(
+                    verify_token(&test_key(), &token, "icarus_auth_test", "icarus_test"),
+                    Err(crate::Error::InvalidToken)
+                ));
+            }
+        }
+
+        assert!(matches!(
+            key::SigningKey::from_pem(key::Algorithm::Hs256, test_key()),
+            Err(crate::Error::UnsupportedKeyFormat)
+        ));
+    }
+
+    #[test]
+    fn test_key_set_rotation() {
+        use josekit::jwk::KeyPair;
+        use josekit::jwk::alg::ec::{EcCurve, EcKeyPair};
+
+        let now =
+            (time::OffsetDateTime::now_utc() - time::OffsetDateTime::UNIX_EPOCH).whole_seconds();
+        let es256_key = || {
+            let key_pair = EcKeyPair::generate(EcCurve::P256).unwrap();
+            key::SigningKey::from_pem(key::Algorithm::Es256, key_pair.to_pem_private_key()).unwrap()
+        };
+
+        let mut key_set = key::KeySet::new();
+        key_set
+            .add_signing_key("2025-01", es256_key(), None, Some(now + 3600))
+            .unwrap();
+        key_set
+            .add_signing_key("2025-02", es256_key(), Some(now + 3600), None)
+            .unwrap();
+        key_set
+            .add_signing_key(
+                "hs256",
+                key::SigningKey::from_secret(test_key()).unwrap(),
+                Some(now + 7200),
+                None,
+            )
+            .unwrap();
+        assert!(matches!(
+            key_set.add_signing_key("2025-01", es256_key(), None, None),
+            Err(crate::Error::DuplicateKeyId)
+        ));
+
+        assert_eq!("2025-01", key_set.signing_entry(now).unwrap().kid);
+        assert_eq!("2025-02", key_set.signing_entry(now + 3600).unwrap().kid);
+        assert_eq!("hs256", key_set.signing_entry(now + 7200).unwrap().kid);
+
+        let (token, _) =
+            create_token_with_key_set(&key_set, &test_resource(), time::Duration::hours(2))
+                .unwrap();
+        let header = josekit::jwt::decode_header(&token).unwrap();
+        assert_eq!(
+            Some("2025-01"),
+            header.claim("kid").and_then(|kid| kid.as_str())
+        );
+
+        // Verifiers only get the public keys, the HS256 secret is never published
+        let jwks = key_set.to_jwks();
+        assert!(!jwks.contains("hs256"));
+        let mut public_set = key::KeySet::from_jwks(&jwks).unwrap();
+        assert_eq!(2, public_set.keys().len());
+        assert!(public_set.signing_entry(now).is_none());
+
+        let claims =
+            verify_token_with_key_set(&public_set, &token, "icarus_auth_test", "icarus_test")
+                .unwrap();
+        assert_eq!("Authorization", claims.sub);
+
+        let (unstamped, _) = create_token_with_key(
+            key_set.keys()[0].signing_key.as_ref().unwrap(),
+            &test_resource(),
+            time::Duration::hours(2),
+        )
+        .unwrap();
+        assert!(matches!(
+            verify_token_with_key_set(&public_set, &unstamped, "icarus_auth_test", "icarus_test"),
+            Err(crate::Error::UnknownKeyId)
+        ));
+
+        // A key that stopped signing before the token was issued does not vouch for it
+        let mut retired = public_set.remove("2025-01").unwrap();
+        retired.not_after = Some(now - 3600);
+        public_set
+            .add_verifying_key("2025-01", retired.verifying_key, None, retired.not_after)
+            .unwrap();
+        assert!(matches!(
+            verify_token_with_key_set(&public_set, &token, "icarus_auth_test", "icarus_test"),
+            Err(crate::Error::InvalidToken)
+        ));
+
+        public_set.remove("2025-01");
+        assert!(matches!(
+            verify_token_with_key_set(&public_set, &token, "icarus_auth_test", "icarus_test"),
+            Err(crate::Error::UnknownKeyId)
+        ));
+    }
+
+    #[test]
+    fn test_private_jwk_not_published() {
+        use josekit::jwk::KeyPair;
+        use josekit::jwk::alg::ec::{EcCurve, EcKeyPair};
+        use josekit::jwk::alg::ed::{EdCurve, EdKeyPair};
+        use josekit::jwk::alg::rsa::RsaKeyPair;
+
+        let private_jwks = [
+            (
+                key::Algorithm::Rs256,
+                RsaKeyPair::generate(2048).unwrap().to_jwk_key_pair(),
+            ),
+            (
+                key::Algorithm::Es256,
+                EcKeyPair::generate(EcCurve::P256)
+                    .unwrap()
+                    .to_jwk_key_pair(),
+            ),
+            (
+                key::Algorithm::EdDsa,
+                EdKeyPair::generate(EdCurve::Ed25519)
+                    .unwrap()
+                    .to_jwk_key_pair(),
+            ),
+        ];
+        let private_members = ["d", "p", "q", "dp", "dq", "qi"];
+
+        let mut key_set = key::KeySet::new();
+        let mut document = josekit::jwk::JwkSet::new();
+        for (index, (algorithm, jwk)) in private_jwks.into_iter().enumerate() {
+            let verifying_key = key::VerifyingKey::from_jwk(algorithm, jwk.to_string()).unwrap();
+            key_set
+                .add_verifying_key(&index.to_string(), verifying_key, None, None)
+                .unwrap();
+
+            let mut jwk = jwk;
+            jwk.set_key_id(index.to_string());
+            document.push_key(jwk);
+        }
+
+        for jwks in [
+            key_set.to_jwks(),
+            key::KeySet::from_jwks(document.to_string())
+                .unwrap()
+                .to_jwks(),
+        ] {
+            let published = josekit::jwk::JwkSet::from_bytes(&jwks).unwrap();
+            assert_eq!(3, published.keys().len());
+            for jwk in published.keys() {
+                for member in private_members {
+                    assert!(jwk.parameter(member).is_none(), "{member} was published");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_refresh_token_rotation() {
+        let mut key_set = key::KeySet::new();
+        key_set
+            .add_signing_key(
+                "hs256",
+                key::SigningKey::from_secret(test_key()).unwrap(),
+                None,
+                None,
+            )
+            .unwrap();
+        let mut test_token_resource = test_resource();
+        test_token_resource.id = uuid::Uuid::new_v4();
+        let access_duration = time::Duration::hours(2);
+        let refresh_duration = time::Duration::days(30);
+
+        let (pair, record) = issue_token_pair(
+            &key_set,
+            &test_token_resource,
+            access_duration,
+            refresh_duration,
+        )
+        .unwrap();
+        assert_eq!(REFRESH_TOKEN_LENGTH, pair.refresh_token.len());
+        assert_ne!(pair.refresh_token, record.token_hash);
+        assert_eq!(RefreshToken::hash(&pair.refresh_token), record.token_hash);
+        assert_eq!(test_token_resource.id, record.user_id);
+        assert!(
+            verify_token_with_key_set(
+                &key_set,
+                &pair.access_token,
+                "icarus_auth_test",
+                "icarus_test"
+            )
+            .is_ok()
+        );
+
+        let mut family = vec![record];
+        let (rotated, rotated_record) = refresh_token_pair(
+            &key_set,
+            &mut family,
+            &pair.refresh_token,
+            &test_token_resource,
+            access_duration,
+            refresh_duration,
+        )
+        .unwrap();
+        assert_ne!(pair.refresh_token, rotated.refresh_token);
+        assert_eq!(family[0].family_id, rotated_record.family_id);
+        assert_eq!(rotated_record.id, family[0].replaced_by);
+        assert!(!family[0].revoked);
+        family.push(rotated_record);
+
+        assert!(matches!(
+            refresh_token_pair(
+                &key_set,
+                &mut family,
+                "not a refresh token",
+                &test_token_resource,
+                access_duration,
+                refresh_duration,
+            ),
+            Err(crate::Error::InvalidToken)
+        ));
+
+        let (_, unrelated) = RefreshToken::generate(test_token_resource.id, refresh_duration);
+        family.push(unrelated);
+
+        // Presenting the rotated token again revokes the family, including the newest token
+        assert!(matches!(
+            refresh_token_pair(
+                &key_set,
+                &mut family,
+                &pair.refresh_token,
+                &test_token_resource,
+                access_duration,
+                refresh_duration,
+            ),
+            Err(crate::Error::RefreshTokenReused)
+        ));
+        assert!(family[..2].iter().all(|token| token.revoked));
+        assert!(!family[2].revoked);
+        assert!(matches!(
+            refresh_token_pair(
+                &key_set,
+                &mut family,
+                &rotated.refresh_token,
+                &test_token_resource,
+                access_duration,
+                refresh_duration,
+            ),
+            Err(crate::Error::RefreshTokenReused)
+        ));
+
+        let (expired, expired_record) =
+            RefreshToken::generate(test_token_resource.id, time::Duration::hours(-1));
+        assert!(matches!(
+            refresh_token_pair(
+                &key_set,
+                &mut [expired_record],
+                &expired,
+                &test_token_resource,
+                access_duration,
+                refresh_duration,
+            ),
+            Err(crate::Error::TokenExpired)
+        ));
+    }
+
+    #[test]
+    fn test_file_revocation_store_concurrent() {
+        use revocation::RevocationStore;
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("revoked.json");
+
+        // Each store has its own lock file handle, like separate processes
+        let handles: Vec<_> = (0..4)
+            .map(|thread| {
+                let path = path.clone();
+                std::thread::spawn(move || {
+                    let store = revocation::FileRevocationStore::open(&path).unwrap();
+                    for index in 0..10 {
+                        store
+                            .revoke(&format!("{thread}-{index}"), i64::MAX / 2)
+                            .unwrap();
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        let store = revocation::FileRevocationStore::open(&path).unwrap();
+        for thread in 0..4 {
+            for index in 0..10 {
+                assert!(store.is_revoked(&format!("{thread}-{index}")).unwrap());
+            }
+        }
+    }
+
+    #[test]
+    fn test_token_revocation() {
+        use revocation::RevocationStore;
+
+        let mut key_set = key::KeySet::new();
+        key_set
+            .add_signing_key(
+                "hs256",
+                key::SigningKey::from_secret(test_key()).unwrap(),
+                None,
+                None,
+            )
+            .unwrap();
+        let create = || {
+            create_token_with_key_set(&key_set, &test_resource(), time::Duration::hours(2))
+                .unwrap()
+                .0
+        };
+        let (token, other_token) = (create(), create());
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("revoked.json");
+        let file_store = revocation::FileRevocationStore::open(&path).unwrap();
+        let stores: Vec<Box<dyn RevocationStore>> = vec![
+            Box::new(revocation::MemoryRevocationStore::new()),
+            Box::new(file_store),
+        ];
+
+        for store in &stores {
+            let claims = verify_token_with_revocation(
+                &key_set,
+                store.as_ref(),
+                &token,
+                "icarus_auth_test",
+                "icarus_test",
+            )
+            .unwrap();
+            assert!(claims.jti.is_some());
+
+            revocation::revoke(store.as_ref(), &claims).unwrap();
+            assert!(matches!(
+                verify_token_with_revocation(
+                    &key_set,
+                    store.as_ref(),
+                    &token,
+                    "icarus_auth_test",
+                    "icarus_test"
+                ),
+                Err(crate::Error::TokenRevoked)
+            ));
+            assert!(
+                verify_token_with_revocation(
+                    &key_set,
+                    store.as_ref(),
+                    &other_token,
+                    "icarus_auth_test",
+                    "icarus_test"
+                )
+                .is_ok()
+            );
+
+            store.revoke("expired", claims.iat - 3600).unwrap();
+            assert_eq!(1, store.prune(claims.iat).unwrap());
+            assert!(!store.is_revoked("expired").unwrap());
+            assert!(store.is_revoked(claims.jti.as_ref().unwrap()).unwrap());
+        }
+
+        let reopened = revocation::FileRevocationStore::open(&path).unwrap();
+        let claims =
+            verify_token_with_key_set(&key_set, &token, "icarus_auth_test", "icarus_test").unwrap();
+        assert!(reopened.is_revoked(claims.jti.as_ref().unwrap()).unwrap());
+        assert!(!reopened.is_revoked("expired").unwrap());
+
+        // Stores sharing a file see each other's changes and do not drop them
+        let other = revocation::FileRevocationStore::open(&path).unwrap();
+        other.revoke("other", claims.exp).unwrap();
+        assert!(reopened.is_revoked("other").unwrap());
+        reopened.revoke("reopened", claims.exp).unwrap();
+        assert!(other.is_revoked("reopened").unwrap());
+        assert!(other.is_revoked(claims.jti.as_ref().unwrap()).unwrap());
+
+        assert!(matches!(
+            verify_token_with_store(
+                &test_key(),
+                &reopened,
+                &token,
+                "icarus_auth_test",
+                "icarus_test"
+            ),
+            Err(crate::Error::TokenRevoked)
+        ));
+        let verifying_key = key::VerifyingKey::from_secret(test_key().as_bytes()).unwrap();
+        assert!(
+            verify_token_with_key_and_store(
+                &verifying_key,
+                &reopened,
+                &other_token,
+                "icarus_auth_test",
+                "icarus_test"
+            )
+            .is_ok()
+        );
+    }
 }
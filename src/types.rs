@@ -1,4 +1,4 @@
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum MusicType {
     DefaultMusicExtension,
     WavExtension,
@@ -7,10 +7,62 @@ pub enum MusicType {
     None,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub enum CoverArtType {
     PngExtension,
     JpegExtension,
     JpgExtension,
+    WebpExtension,
+    GifExtension,
     None,
 }
+
+/// How saving a file treats an existing file at the same path
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum WriteMode {
+    /// Replaces the existing file
+    #[default]
+    Overwrite,
+    /// Fails when a file already exists
+    CreateNew,
+}
+
+impl MusicType {
+    /// Gets the MusicType of a file extension such as ".flac" or "flac"
+    pub fn from_extension(extension: &str) -> MusicType {
+        let extension = extension.trim_start_matches('.');
+        let matches = |constant: &str| extension.eq_ignore_ascii_case(&constant[1..]);
+
+        if matches(crate::constants::file_extensions::audio::FLACEXTENSION) {
+            MusicType::FlacExtension
+        } else if matches(crate::constants::file_extensions::audio::WAVEXTENSION) {
+            MusicType::WavExtension
+        } else if matches(crate::constants::file_extensions::audio::MPTHREEEXTENSION) {
+            MusicType::MPThreeExtension
+        } else {
+            MusicType::None
+        }
+    }
+}
+
+impl CoverArtType {
+    /// Gets the CoverArtType of a file extension such as ".png" or "png"
+    pub fn from_extension(extension: &str) -> CoverArtType {
+        let extension = extension.trim_start_matches('.');
+        let matches = |constant: &str| extension.eq_ignore_ascii_case(&constant[1..]);
+
+        if matches(crate::constants::file_extensions::image::PNGEXTENSION) {
+            CoverArtType::PngExtension
+        } else if matches(crate::constants::file_extensions::image::JPEGEXTENSION) {
+            CoverArtType::JpegExtension
+        } else if matches(crate::constants::file_extensions::image::JPGEXTENSION) {
+            CoverArtType::JpgExtension
+        } else if matches(crate::constants::file_extensions::image::WEBPEXTENSION) {
+            CoverArtType::WebpExtension
+        } else if matches(crate::constants::file_extensions::image::GIFEXTENSION) {
+            CoverArtType::GifExtension
+        } else {
+            CoverArtType::None
+        }
+    }
+}
@@ -0,0 +1,196 @@
+//! Crash-safe file writes. Data is written to a temporary file in the destination's directory,
+//! synced, then renamed over the destination so a crash never leaves a partially written file
+//! under the final name
+
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+use rand::Rng;
+
+use crate::types::WriteMode;
+
+/// Length of the random part of temporary filenames
+const TEMP_SUFFIX_LENGTH: usize = 12;
+
+/// Atomically writes data to a file
+pub fn write(path: &Path, data: &[u8], mode: WriteMode) -> Result<(), crate::Error> {
+    write_with(path, mode, |writer| writer.write_all(data))
+}
+
+/// Atomically writes a file from a reader. Returns the number of bytes written
+pub fn write_from_reader<R: std::io::Read>(
+    path: &Path,
+    reader: &mut R,
+    mode: WriteMode,
+) -> Result<u64, crate::Error> {
+    write_with(path, mode, |writer| std::io::copy(reader, writer))
+}
+
+/// Atomically writes a file with the given function
+pub fn write_with<T, F>(path: &Path, mode: WriteMode, write: F) -> Result<T, crate::Error>
+where
+    F: FnOnce(&mut std::io::BufWriter<std::fs::File>) -> Result<T, std::io::Error>,
+{
+    if mode == WriteMode::CreateNew && path.exists() {
+        return Err(crate::Error::FileAlreadyExists);
+    }
+
+    let directory = parent_directory(path);
+    let (temp_path, file) = create_temp_file(path)?;
+
+    let result = write_temp_file(file, write).and_then(|value| {
+        commit(&temp_path, path, mode)?;
+        sync_directory(&directory)?;
+        Ok(value)
+    });
+
+    if result.is_err() {
+        let _ = std::fs::remove_file(&temp_path);
+    }
+
+    result
+}
+
+fn write_temp_file<T, F>(file: std::fs::File, write: F) -> Result<T, crate::Error>
+where
+    F: FnOnce(&mut std::io::BufWriter<std::fs::File>) -> Result<T, std::io::Error>,
+{
+    let mut writer = std::io::BufWriter::new(file);
+    let value = write(&mut writer)?;
+    let file = writer.into_inner().map_err(|err| err.into_error())?;
+    file.sync_all()?;
+    Ok(value)
+}
+
+/// Moves the temporary file to its destination
+fn commit(temp_path: &Path, path: &Path, mode: WriteMode) -> Result<(), crate::Error> {
+    match mode {
+        WriteMode::Overwrite => Ok(std::fs::rename(temp_path, path)?),
+        // Linking fails when the destination exists, unlike renaming
+        WriteMode::CreateNew => match std::fs::hard_link(temp_path, path) {
+            Ok(_) => Ok(std::fs::remove_file(temp_path)?),
+            Err(err) if err.kind() == std::io::ErrorKind::AlreadyExists => {
+                Err(crate::Error::FileAlreadyExists)
+            }
+            // Some filesystems do not support links. Copying into a file that is created
+            // exclusively still never replaces an existing destination
+            Err(_) => copy_new(temp_path, path),
+        },
+    }
+}
+
+/// Copies the temporary file into a newly created destination, then removes it
+fn copy_new(temp_path: &Path, path: &Path) -> Result<(), crate::Error> {
+    let mut destination = match std::fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)
+    {
+        Ok(file) => file,
+        Err(err) if err.kind() == std::io::ErrorKind::AlreadyExists => {
+            return Err(crate::Error::FileAlreadyExists);
+        }
+        Err(err) => return Err(crate::Error::Io(err)),
+    };
+
+    let copied = std::fs::File::open(temp_path)
+        .and_then(|mut source| std::io::copy(&mut source, &mut destination))
+        .and_then(|_| destination.sync_all());
+    match copied {
+        Ok(_) => Ok(std::fs::remove_file(temp_path)?),
+        Err(err) => {
+            let _ = std::fs::remove_file(path);
+            Err(crate::Error::Io(err))
+        }
+    }
+}
+
+/// Creates a temporary file next to the destination
+fn create_temp_file(path: &Path) -> Result<(PathBuf, std::fs::File), crate::Error> {
+    let filename = match path.file_name() {
+        Some(filename) => filename.to_string_lossy(),
+        None => return Err(crate::Error::FilenameNotInitialized),
+    };
+
+    loop {
+        let suffix: String = rand::rng()
+            .sample_iter(rand::distr::Alphanumeric)
+            .take(TEMP_SUFFIX_LENGTH)
+            .map(char::from)
+            .collect();
+        let temp_path = path.with_file_name(format!(".{filename}.{suffix}.tmp"));
+
+        match std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&temp_path)
+        {
+            Ok(file) => return Ok((temp_path, file)),
+            Err(err) if err.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(err) => return Err(crate::Error::Io(err)),
+        }
+    }
+}
+
+fn parent_directory(path: &Path) -> PathBuf {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent.to_path_buf(),
+        _ => PathBuf::from("."),
+    }
+}
+
+/// Syncs a directory so a rename within it survives a crash
+#[cfg(unix)]
+fn sync_directory(directory: &Path) -> Result<(), std::io::Error> {
+    std::fs::File::open(directory)?.sync_all()
+}
+
+/// Directories cannot be opened for syncing outside of unix
+#[cfg(not(unix))]
+fn sync_directory(_directory: &Path) -> Result<(), std::io::Error> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::types::WriteMode;
+
+    #[test]
+    fn test_write() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let path = temp_dir.path().join("file.bin");
+
+        super::write(&path, b"first", WriteMode::CreateNew).unwrap();
+        let err = super::write(&path, b"second", WriteMode::CreateNew).unwrap_err();
+        assert!(matches!(err, crate::Error::FileAlreadyExists));
+        assert_eq!(b"first", &std::fs::read(&path).unwrap()[..]);
+
+        super::write(&path, b"second", WriteMode::Overwrite).unwrap();
+        assert_eq!(b"second", &std::fs::read(&path).unwrap()[..]);
+
+        let failed: Result<(), crate::Error> =
+            super::write_with(&path, WriteMode::Overwrite, |_| {
+                Err(std::io::Error::other("failed"))
+            });
+        assert!(failed.is_err());
+        assert_eq!(b"second", &std::fs::read(&path).unwrap()[..]);
+        assert_eq!(1, std::fs::read_dir(temp_dir.path()).unwrap().count());
+    }
+
+    #[test]
+    fn test_copy_new() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let temp_path = temp_dir.path().join("file.tmp");
+        let path = temp_dir.path().join("file.bin");
+
+        std::fs::write(&temp_path, b"first").unwrap();
+        super::copy_new(&temp_path, &path).unwrap();
+        assert_eq!(b"first", &std::fs::read(&path).unwrap()[..]);
+        assert!(!temp_path.exists());
+
+        std::fs::write(&temp_path, b"second").unwrap();
+        let err = super::copy_new(&temp_path, &path).unwrap_err();
+        assert!(matches!(err, crate::Error::FileAlreadyExists));
+        assert_eq!(b"first", &std::fs::read(&path).unwrap()[..]);
+    }
+}
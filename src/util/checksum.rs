@@ -0,0 +1,72 @@
+//! SHA-256 checksums of song and cover art files
+
+use std::io::Read;
+
+use sha2::{Digest, Sha256};
+
+/// Size of the reads done while hashing a stream
+const READ_LENGTH: usize = 64 * 1024;
+/// Length of a hex encoded SHA-256 checksum
+pub const SHA256_HEX_LENGTH: usize = 64;
+
+/// Gets the hex encoded SHA-256 checksum of data
+pub fn sha256(data: &[u8]) -> String {
+    to_hex(&Sha256::digest(data))
+}
+
+/// Gets the hex encoded SHA-256 checksum of a stream, reading it in chunks
+pub fn sha256_from_reader<R: Read>(reader: &mut R) -> Result<String, std::io::Error> {
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; READ_LENGTH];
+
+    loop {
+        match reader.read(&mut buffer) {
+            Ok(0) => break,
+            Ok(read) => hasher.update(&buffer[..read]),
+            Err(err) if err.kind() == std::io::ErrorKind::Interrupted => {}
+            Err(err) => return Err(err),
+        }
+    }
+
+    Ok(to_hex(&hasher.finalize()))
+}
+
+/// Gets the hex encoded SHA-256 checksum of a file
+pub fn sha256_from_file<P: AsRef<std::path::Path>>(path: P) -> Result<String, std::io::Error> {
+    let mut file = std::fs::File::open(path)?;
+    sha256_from_reader(&mut file)
+}
+
+/// Checks that a value is a lowercase hex encoded SHA-256 checksum
+pub fn is_sha256_hex(value: &str) -> bool {
+    value.len() == SHA256_HEX_LENGTH && is_lowercase_hex(value)
+}
+
+/// Checks that a value only holds lowercase hex digits
+pub fn is_lowercase_hex(value: &str) -> bool {
+    value
+        .bytes()
+        .all(|byte| byte.is_ascii_digit() || (b'a'..=b'f').contains(&byte))
+}
+
+/// Encodes bytes as lowercase hex
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_sha256() {
+        let expected = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+        assert_eq!(expected, super::sha256(b"abc"));
+        assert_eq!(
+            expected,
+            super::sha256_from_reader(&mut &b"abc"[..]).unwrap()
+        );
+
+        assert!(super::is_sha256_hex(expected));
+        assert!(!super::is_sha256_hex(&expected.to_uppercase()));
+        assert!(!super::is_sha256_hex(&expected[1..]));
+    }
+}
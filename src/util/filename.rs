@@ -0,0 +1,158 @@
+//! Random filename generation shared by songs and cover art
+
+use std::path::Path;
+
+use rand::Rng;
+
+/// Characters random filenames are drawn from by default
+pub const DEFAULT_ALPHABET: &str = "abcdefghijklmnopqrstuvwxyz0123456789";
+/// Number of random characters in a filename by default
+pub const DEFAULT_LENGTH: usize = 16;
+/// Number of filenames tried before giving up on finding one not already taken
+pub const MAX_ATTEMPTS: usize = 16;
+
+/// How the random part of a filename is produced
+#[derive(Clone, Debug, PartialEq)]
+pub enum Naming {
+    /// Characters drawn from an alphabet
+    Random { alphabet: String, length: usize },
+    /// A random (v4) UUID in its hyphenated form
+    Uuid,
+}
+
+impl Default for Naming {
+    fn default() -> Self {
+        Naming::Random {
+            alphabet: String::from(DEFAULT_ALPHABET),
+            length: DEFAULT_LENGTH,
+        }
+    }
+}
+
+/// Generates filenames made of a prefix, a random part and an extension, such as
+/// `track-3k9x0q7c1m2v8b4z.flac`. Randomness comes from a cryptographically secure generator
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Generator {
+    pub prefix: String,
+    pub naming: Naming,
+}
+
+impl Generator {
+    /// Creates a generator drawing from the default alphabet and length
+    pub fn new(prefix: &str) -> Generator {
+        Generator {
+            prefix: String::from(prefix),
+            naming: Naming::default(),
+        }
+    }
+
+    /// Creates a generator naming files with random UUIDs
+    pub fn uuid(prefix: &str) -> Generator {
+        Generator {
+            prefix: String::from(prefix),
+            naming: Naming::Uuid,
+        }
+    }
+
+    /// Generates a filename. The extension may be given with or without its dot
+    pub fn generate(&self, extension: &str) -> Result<String, crate::Error> {
+        let random = match &self.naming {
+            Naming::Random { alphabet, length } => {
+                let characters: Vec<char> = alphabet.chars().collect();
+                if characters.is_empty() || *length == 0 {
+                    return Err(crate::Error::InvalidFilename);
+                }
+
+                let mut rng = rand::rng();
+                (0..*length)
+                    .map(|_| characters[rng.random_range(0..characters.len())])
+                    .collect::<String>()
+            }
+            Naming::Uuid => uuid::Uuid::new_v4().to_string(),
+        };
+
+        let extension = extension.trim_start_matches('.');
+        let filename = if extension.is_empty() {
+            format!("{}{random}", self.prefix)
+        } else {
+            format!("{}{random}.{extension}", self.prefix)
+        };
+
+        crate::util::path::validate_filename(&filename)?;
+        Ok(filename)
+    }
+
+    /// Generates a filename not already taken in a directory of a storage backend
+    pub fn generate_unique<S: crate::storage::Storage>(
+        &self,
+        storage: &S,
+        directory: &Path,
+        extension: &str,
+    ) -> Result<String, crate::Error> {
+        for _ in 0..MAX_ATTEMPTS {
+            let filename = self.generate(extension)?;
+            if !storage.exists(&crate::util::path::join(directory, &filename)?)? {
+                return Ok(filename);
+            }
+        }
+
+        Err(crate::Error::FilenameCollision)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use crate::storage::Storage;
+
+    #[test]
+    fn test_generate_exact_length() {
+        let generator = super::Generator {
+            prefix: String::from("track-"),
+            naming: super::Naming::Random {
+                alphabet: String::from("ab"),
+                length: 40,
+            },
+        };
+
+        for _ in 0..100 {
+            let filename = generator.generate(".flac").unwrap();
+            let random = &filename["track-".len()..filename.len() - ".flac".len()];
+            assert_eq!(40, random.len());
+            assert!(random.chars().all(|c| c == 'a' || c == 'b'));
+        }
+
+        let filename = super::Generator::uuid("").generate("png").unwrap();
+        assert!(uuid::Uuid::parse_str(filename.trim_end_matches(".png")).is_ok());
+    }
+
+    #[test]
+    fn test_generate_unique() {
+        let storage = crate::storage::MemoryStorage::new();
+        let generator = super::Generator {
+            prefix: String::new(),
+            naming: super::Naming::Random {
+                alphabet: String::from("a"),
+                length: 1,
+            },
+        };
+
+        let filename = generator
+            .generate_unique(&storage, Path::new("/music"), "flac")
+            .unwrap();
+        assert_eq!("a.flac", filename);
+
+        storage
+            .put(
+                Path::new("/music/a.flac"),
+                &[1],
+                crate::types::WriteMode::CreateNew,
+            )
+            .unwrap();
+        assert!(matches!(
+            generator.generate_unique(&storage, Path::new("/music"), "flac"),
+            Err(crate::Error::FilenameCollision)
+        ));
+    }
+}
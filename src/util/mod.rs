@@ -1,8 +1,15 @@
+pub mod atomic;
+pub mod checksum;
+pub mod filename;
+pub mod path;
+pub(crate) mod sync;
+
+#[deprecated(note = "use util::path::join, which rejects unsafe filenames")]
 pub fn concatenate_path(
     directory: &str,
     filename: &str,
     last_index: usize,
-) -> Result<String, std::io::Error> {
+) -> Result<String, crate::Error> {
     if let Some(character) = directory.chars().nth(last_index) {
         let buffer: String = if character != '/' {
             format!("{directory}/")
@@ -12,8 +19,6 @@ pub fn concatenate_path(
 
         Ok(format!("{buffer}{filename}"))
     } else {
-        Err(std::io::Error::other(
-            crate::constants::error::LAST_CHARACTER_IN_DIRECTORY,
-        ))
+        Err(crate::Error::LastCharacterInDirectory)
     }
 }
@@ -0,0 +1,83 @@
+//! Joining of storage directories and filenames. Filenames can come from clients, so they are
+//! checked to stay within the directory
+
+use std::path::{Component, Path, PathBuf};
+
+/// Joins a directory and a filename. Trailing separators on the directory are handled, and
+/// filenames that are absolute, contain separators or refer to a parent directory are rejected
+pub fn join<D: AsRef<Path>, F: AsRef<Path>>(
+    directory: D,
+    filename: F,
+) -> Result<PathBuf, crate::Error> {
+    let directory = directory.as_ref();
+    let filename = filename.as_ref();
+
+    if directory.as_os_str().is_empty() {
+        return Err(crate::Error::DirectoryNotInitialized);
+    } else if filename.as_os_str().is_empty() {
+        return Err(crate::Error::FilenameNotInitialized);
+    }
+
+    validate_filename(filename)?;
+    Ok(directory.join(filename))
+}
+
+/// Verifies that a filename is a single plain path component
+pub fn validate_filename<F: AsRef<Path>>(filename: F) -> Result<(), crate::Error> {
+    let mut components = filename.as_ref().components();
+
+    match (components.next(), components.next()) {
+        (Some(Component::Normal(_)), None) => Ok(()),
+        _ => Err(crate::Error::InvalidFilename),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_join() {
+        let expected = PathBuf::from("/music/track01.flac");
+        assert_eq!(expected, super::join("/music", "track01.flac").unwrap());
+        assert_eq!(expected, super::join("/music/", "track01.flac").unwrap());
+    }
+
+    #[test]
+    fn test_join_rejects_unsafe_filenames() {
+        for filename in [
+            "..",
+            "../track01.flac",
+            "/etc/passwd",
+            "a/b.flac",
+            "./a.flac",
+            ".",
+        ] {
+            assert!(
+                matches!(
+                    super::join("/music", filename),
+                    Err(crate::Error::InvalidFilename)
+                ),
+                "{filename} was accepted"
+            );
+        }
+        assert!(matches!(
+            super::join("", "track01.flac"),
+            Err(crate::Error::DirectoryNotInitialized)
+        ));
+        assert!(matches!(
+            super::join("/music", ""),
+            Err(crate::Error::FilenameNotInitialized)
+        ));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_join_non_utf8() {
+        use std::os::unix::ffi::OsStrExt;
+
+        let filename = std::ffi::OsStr::from_bytes(b"track\xff.flac");
+        let path = super::join("/music", filename).unwrap();
+        assert_eq!(filename, path.file_name().unwrap());
+    }
+}
@@ -0,0 +1,12 @@
+//! Helpers for shared state
+
+use std::sync::{Mutex, MutexGuard};
+
+/// Locks a mutex, recovering the data when another thread panicked while holding the lock. Only
+/// used for data that is updated in a single step, so it is never left half updated
+pub fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    match mutex.lock() {
+        Ok(guard) => guard,
+        Err(poisoned) => poisoned.into_inner(),
+    }
+}
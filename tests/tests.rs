@@ -93,6 +93,37 @@ mod song_tests {
         );
     }
 
+    #[test]
+    fn test_song_path_rejects_traversal() {
+        let mut song = song::Song {
+            directory: utils::get_tests_directory(),
+            filename: String::from("track01.flac"),
+            ..Default::default()
+        };
+        match song.path() {
+            Ok(path) => {
+                assert!(path.exists());
+                assert_eq!(path.to_string_lossy(), song.song_path().unwrap());
+            }
+            Err(err) => panic!("Could not get song path: {err:?}"),
+        }
+
+        for filename in ["../Cargo.toml", "/etc/passwd", "sub/track01.flac"] {
+            song.filename = String::from(filename);
+            assert!(matches!(
+                song.path(),
+                Err(icarus_models::Error::InvalidFilename)
+            ));
+            assert!(song.save_to_filesystem().is_err());
+        }
+
+        song.directory = String::new();
+        assert!(matches!(
+            song.song_path(),
+            Err(icarus_models::Error::DirectoryNotInitialized)
+        ));
+    }
+
     #[test]
     fn test_song_generate_filename() {
         let mut song = song::Song::default();
@@ -152,6 +183,884 @@ mod song_tests {
             }
         }
     }
+
+    #[test]
+    fn test_detect_music_type() {
+        let expected = [
+            ("track01.flac", types::MusicType::FlacExtension),
+            ("sample.mp3", types::MusicType::MPThreeExtension),
+            ("sample.wav", types::MusicType::WavExtension),
+            ("album.json", types::MusicType::None),
+        ];
+
+        for (filename, music_type) in expected {
+            let filepath = utils::get_tests_directory() + filename;
+            match utils::extract_data_from_file(&filepath) {
+                Ok(data) => assert_eq!(music_type, song::detect_music_type(&data)),
+                Err(err) => panic!("Failed to open file: {err:?}"),
+            }
+        }
+
+        let mut data = b"ID3\x04\0\0\0\0\0\0".to_vec();
+        data.extend_from_slice(b"fLaC");
+        assert_eq!(
+            types::MusicType::MPThreeExtension,
+            song::detect_music_type(&data)
+        );
+    }
+
+    #[test]
+    fn test_save_validated_rejects_mismatch() {
+        let temp_dir = tempdir().expect("Failed to create temp dir");
+        let filepath = utils::get_tests_directory() + "sample.mp3";
+        let mut song = song::Song {
+            directory: String::from(temp_dir.path().to_str().unwrap()),
+            filename: String::from("track-upload.flac"),
+            data: utils::extract_data_from_file(&filepath).unwrap(),
+            ..Default::default()
+        };
+
+        assert!(song.save_to_filesystem_validated().is_err());
+        assert!(!temp_dir.path().join(&song.filename).exists());
+
+        song.filename = String::from("track-upload.mp3");
+        match song.save_to_filesystem_validated() {
+            Ok(_) => assert!(temp_dir.path().join(&song.filename).exists()),
+            Err(err) => panic!("Error saving song: {err:?}"),
+        }
+    }
+
+    #[test]
+    fn test_song_checksums() {
+        let temp_dir = tempdir().expect("Failed to create temp dir");
+        let mut source = song::Song {
+            directory: utils::get_tests_directory(),
+            filename: String::from("track01.flac"),
+            ..Default::default()
+        };
+        if let Err(err) = source.update_checksums() {
+            panic!("Error computing checksums: {err:?}");
+        }
+        assert_eq!(64, source.checksum.len());
+        assert_eq!("48b82969ae653dfc9447bf351976f02d", source.audio_md5);
+        assert!(source.verify_file().unwrap());
+
+        source.data = song::io::to_data(&source).unwrap();
+        assert_eq!(source.checksum, source.compute_checksum().unwrap());
+        assert_eq!(source.audio_md5, source.compute_audio_md5().unwrap());
+
+        let mut song = song::Song {
+            directory: String::from(temp_dir.path().to_str().unwrap()),
+            filename: String::from("track01.flac"),
+            checksum: source.checksum.clone(),
+            ..Default::default()
+        };
+        song::io::copy_song(&source, &mut song).unwrap();
+        song.data = Vec::new();
+        assert!(song.verify_file().unwrap());
+
+        song.title = String::from("Retagged");
+        song::io::write_metadata(&song).unwrap();
+        assert!(!song.verify_file().unwrap());
+        assert_eq!(source.audio_md5, song.compute_audio_md5().unwrap());
+
+        let mp3 = song::Song {
+            directory: utils::get_tests_directory(),
+            filename: String::from("sample.mp3"),
+            ..Default::default()
+        };
+        assert!(mp3.verify_file().is_err());
+        assert!(mp3.compute_audio_md5().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_save_song_with_mode() {
+        let temp_dir = tempdir().expect("Failed to create temp dir");
+        let mut song = song::Song {
+            directory: String::from(temp_dir.path().to_str().unwrap()),
+            filename: String::from("track-mode.flac"),
+            data: vec![1, 2, 3],
+            ..Default::default()
+        };
+
+        song.save_to_filesystem_with_mode(types::WriteMode::CreateNew)
+            .unwrap();
+        song.data = vec![4, 5, 6];
+        match song.save_to_filesystem_with_mode(types::WriteMode::CreateNew) {
+            Ok(_) => panic!("Existing song was overwritten"),
+            Err(err) => assert!(matches!(err, icarus_models::Error::FileAlreadyExists)),
+        }
+        assert_eq!(vec![1, 2, 3], song::io::to_data(&song).unwrap());
+
+        song.save_to_filesystem().unwrap();
+        assert_eq!(vec![4, 5, 6], song::io::to_data(&song).unwrap());
+
+        let entries: Vec<_> = std::fs::read_dir(temp_dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name())
+            .collect();
+        assert_eq!(vec![std::ffi::OsString::from("track-mode.flac")], entries);
+    }
+
+    #[test]
+    fn test_song_streaming() {
+        use std::io::Read;
+
+        let temp_dir = tempdir().expect("Failed to create temp dir");
+        let source = song::Song {
+            directory: utils::get_tests_directory(),
+            filename: String::from("track02.flac"),
+            ..Default::default()
+        };
+        let expected = song::io::to_data(&source).unwrap();
+        let mut target = song::Song {
+            directory: String::from(temp_dir.path().to_str().unwrap()),
+            filename: String::from("track02-streamed.flac"),
+            ..Default::default()
+        };
+
+        match song::io::copy_song_file(&source, &target) {
+            Ok(copied) => assert_eq!(expected.len() as u64, copied),
+            Err(err) => panic!("Error copying song: {err:?}"),
+        }
+        assert!(song::io::copy_song_file(&source, &target).is_err());
+        assert_eq!(expected, song::io::to_data(&target).unwrap());
+        assert_eq!(expected.len() as u64, song::io::file_size(&target).unwrap());
+
+        let mut range: Vec<u8> = Vec::new();
+        song::io::open_range(&target, 4, Some(42))
+            .unwrap()
+            .read_to_end(&mut range)
+            .unwrap();
+        assert_eq!(expected[4..42], range[..]);
+
+        range.clear();
+        let start = expected.len() as u64 - 10;
+        song::io::open_range(&target, start, Some(u64::MAX))
+            .unwrap()
+            .read_to_end(&mut range)
+            .unwrap();
+        assert_eq!(expected[expected.len() - 10..], range[..]);
+        assert!(song::io::open_range(&target, expected.len() as u64, None).is_err());
+
+        target.filename = String::from("track02-from-reader.flac");
+        let mut reader = song::io::open_reader(&source).unwrap();
+        assert_eq!(
+            expected.len() as u64,
+            target.save_from_reader(&mut reader).unwrap()
+        );
+        assert_eq!(expected, song::io::to_data(&target).unwrap());
+    }
+
+    #[test]
+    fn test_generated_filename_length() {
+        for _ in 0..50 {
+            let filename = song::generate_filename(types::MusicType::FlacExtension, true).unwrap();
+            assert_eq!("track-".len() + 16 + ".flac".len(), filename.len());
+
+            let filename =
+                icarus_models::coverart::generate_filename(types::CoverArtType::PngExtension, true)
+                    .unwrap();
+            assert_eq!("coverart-".len() + 16 + ".png".len(), filename.len());
+        }
+    }
+
+    #[test]
+    fn test_render_filename_template() {
+        use std::path::PathBuf;
+
+        let song = song::Song {
+            title: String::from("What? Yes: No"),
+            artist: String::from("AC/DC"),
+            album: String::from("Sample Tracks! Yes"),
+            year: 2025,
+            track: 3,
+            disc: 1,
+            filename: String::from("track-abc.flac"),
+            ..Default::default()
+        };
+
+        match song::template::render(song::template::DEFAULT_TEMPLATE, &song) {
+            Ok(path) => assert_eq!(
+                PathBuf::from("AC_DC/Sample Tracks! Yes (2025)/01-03 What_ Yes_ No.flac"),
+                path
+            ),
+            Err(err) => panic!("Error rendering template: {err:?}"),
+        }
+
+        let mut long = song.clone();
+        long.title = "é".repeat(300);
+        let path = song::template::render("{title}{ext}", &long).unwrap();
+        let filename = path.to_str().unwrap();
+        assert!(filename.len() <= song::template::MAX_COMPONENT_LENGTH);
+        assert!(filename.ends_with("é.flac"));
+
+        let songs = vec![song.clone(), song.clone(), song];
+        let paths = song::template::render_all("{track:02} {title}{ext}", &songs).unwrap();
+        assert_eq!(
+            vec![
+                PathBuf::from("03 What_ Yes_ No.flac"),
+                PathBuf::from("03 What_ Yes_ No (2).flac"),
+                PathBuf::from("03 What_ Yes_ No (3).flac"),
+            ],
+            paths
+        );
+
+        let mut dotted = songs[0].clone();
+        dotted.title = format!("a.{}", "b".repeat(253));
+        let paths = song::template::render_all("{title}", &[dotted.clone(), dotted]).unwrap();
+        let filename = paths[1].to_str().unwrap();
+        assert_eq!(song::template::MAX_COMPONENT_LENGTH, filename.len());
+        assert!(filename.starts_with("a.b") && filename.ends_with("b (2)"));
+
+        assert_eq!("con_.flac", song::template::sanitize("con.flac"));
+        assert_eq!("_", song::template::sanitize(".."));
+        for template in ["{unknown}", "{title", "{track:xx}"] {
+            assert!(matches!(
+                song::template::render(template, &songs[0]),
+                Err(icarus_models::Error::InvalidTemplate)
+            ));
+        }
+    }
+
+    #[test]
+    fn test_group_duplicates() {
+        let song = |title: &str, duration: i32, checksum: &str, audio_md5: &str| song::Song {
+            title: String::from(title),
+            artist: String::from("KD"),
+            album: String::from("Sample Tracks! Yes"),
+            duration,
+            checksum: String::from(checksum),
+            audio_md5: String::from(audio_md5),
+            ..Default::default()
+        };
+        let songs = vec![
+            song("Check D out", 33, "aa", "01"),
+            song("Aaaaaye", 30, "bb", "02"),
+            song("Check D out", 33, "aa", "01"),
+            song("Aaaaaye (retagged)", 30, "cc", "02"),
+            song("check d out!", 34, "dd", ""),
+            song("  CHECK D   OUT", 35, "ee", ""),
+            song("Check D out", 40, "ff", ""),
+            song("Something else", 22, "gg", ""),
+        ];
+
+        let duplicates = song::dedupe::group(songs);
+
+        assert_eq!(2, duplicates.exact.len());
+        assert_eq!(
+            song::dedupe::MatchReason::Checksum,
+            duplicates.exact[0].reason
+        );
+        assert_eq!(2, duplicates.exact[0].songs.len());
+        assert_eq!(
+            song::dedupe::MatchReason::AudioMd5,
+            duplicates.exact[1].reason
+        );
+        assert_eq!("Aaaaaye", duplicates.exact[1].songs[0].title);
+
+        assert_eq!(1, duplicates.probable.len());
+        assert_eq!(
+            song::dedupe::MatchReason::Metadata,
+            duplicates.probable[0].reason
+        );
+        let checksums: Vec<&str> = duplicates.probable[0]
+            .songs
+            .iter()
+            .map(|song| song.checksum.as_str())
+            .collect();
+        assert_eq!(vec!["dd", "ee"], checksums);
+
+        let checksums: Vec<&str> = duplicates
+            .unique
+            .iter()
+            .map(|song| song.checksum.as_str())
+            .collect();
+        assert_eq!(vec!["ff", "gg"], checksums);
+        assert_eq!("check d out", song::dedupe::normalize("  Check, D-out!"));
+    }
+
+    #[test]
+    fn test_group_duplicates_duration_does_not_chain() {
+        let songs: Vec<song::Song> = (100..=120)
+            .step_by(2)
+            .map(|duration| song::Song {
+                title: String::from("Check D out"),
+                artist: String::from("KD"),
+                duration,
+                checksum: format!("{duration}"),
+                ..Default::default()
+            })
+            .collect();
+
+        let duplicates = song::dedupe::group(songs);
+
+        let durations: Vec<Vec<i32>> = duplicates
+            .probable
+            .iter()
+            .map(|cluster| cluster.songs.iter().map(|song| song.duration).collect())
+            .collect();
+        assert_eq!(
+            vec![
+                vec![100, 102],
+                vec![104, 106],
+                vec![108, 110],
+                vec![112, 114],
+                vec![116, 118]
+            ],
+            durations
+        );
+        assert_eq!(1, duplicates.unique.len());
+        assert_eq!(120, duplicates.unique[0].duration);
+    }
+}
+
+#[cfg(test)]
+mod metadata_tests {
+    use tempfile::tempdir;
+
+    use crate::utils;
+    use icarus_models::album;
+    use icarus_models::metadata::flac;
+    use icarus_models::song;
+
+    #[test]
+    fn test_read_flac_metadata() {
+        let album_file = utils::get_tests_directory() + "album.json";
+        let album = match album::collection::parse_album(&album_file) {
+            Ok(album) => album,
+            Err(err) => panic!("Error parsing album json file: {err:?}"),
+        };
+
+        for (index, track) in album.tracks.iter().enumerate() {
+            let filename = format!("track{:02}.flac", index + 1);
+            match song::Song::from_flac_file(&utils::get_tests_directory(), &filename) {
+                Ok(song) => {
+                    assert_eq!(track.duration.round() as i32, song.duration);
+                    assert_eq!(2025, song.year);
+                    assert_eq!(filename, song.filename);
+                }
+                Err(err) => panic!("Error reading metadata of {filename}: {err:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_write_flac_metadata() {
+        let temp_dir = tempdir().expect("Failed to create temp dir");
+        let mut source = song::Song {
+            directory: utils::get_tests_directory(),
+            filename: String::from("track01.flac"),
+            ..Default::default()
+        };
+        source.data = song::io::to_data(&source).unwrap();
+        let mut song = song::Song {
+            directory: String::from(temp_dir.path().to_str().unwrap()),
+            filename: String::from("track01.flac"),
+            ..Default::default()
+        };
+        if let Err(err) = song::io::copy_song(&source, &mut song) {
+            panic!("Error copying song: {err:?}");
+        }
+
+        song.title = String::from("E less");
+        song.artist = String::from("KD");
+        song.album = String::from("Sample Tracks! Yes");
+        song.album_artist = String::from("KD");
+        song.genre = String::from("Country");
+        song.year = 2024;
+        song.track = 1;
+        song.track_count = 3;
+        song.disc = 1;
+        song.disc_count = 1;
+
+        if let Err(err) = song::io::write_metadata(&song) {
+            panic!("Error writing metadata: {err:?}");
+        }
+
+        match song::Song::from_flac_file(&song.directory, &song.filename) {
+            Ok(written) => {
+                assert_eq!(song.title, written.title);
+                assert_eq!(song.artist, written.artist);
+                assert_eq!(song.album, written.album);
+                assert_eq!(song.album_artist, written.album_artist);
+                assert_eq!(song.genre, written.genre);
+                assert_eq!(song.year, written.year);
+                assert_eq!(song.track, written.track);
+                assert_eq!(song.track_count, written.track_count);
+                assert_eq!(song.disc, written.disc);
+                assert_eq!(song.disc_count, written.disc_count);
+                assert_eq!(31, written.duration);
+            }
+            Err(err) => panic!("Error reading metadata: {err:?}"),
+        }
+
+        let original = source.data;
+        let written = song::io::to_data(&song).unwrap();
+        let audio_length = original.len() - original_header_length(&original);
+        assert_eq!(
+            original[original.len() - audio_length..],
+            written[written.len() - audio_length..]
+        );
+    }
+
+    #[test]
+    fn test_read_mp3_metadata() {
+        match song::Song::from_file(&utils::get_tests_directory(), "sample.mp3") {
+            Ok(song) => {
+                assert_eq!("Check D out", song.title);
+                assert_eq!("KD", song.artist);
+                assert_eq!("Sample Tracks! Yes", song.album);
+                assert_eq!("Country", song.genre);
+                assert_eq!(2025, song.year);
+                assert_eq!(3, song.track);
+                assert_eq!(3, song.track_count);
+            }
+            Err(err) => panic!("Error reading metadata: {err:?}"),
+        }
+    }
+
+    #[test]
+    fn test_read_wav_metadata() {
+        match song::Song::from_file(&utils::get_tests_directory(), "sample.wav") {
+            Ok(song) => {
+                assert_eq!("Aaaaaye", song.title);
+                assert_eq!("KD", song.artist);
+                assert_eq!(2025, song.year);
+                assert_eq!(2, song.track);
+                assert_eq!(2, song.duration);
+            }
+            Err(err) => panic!("Error reading metadata: {err:?}"),
+        }
+    }
+
+    #[test]
+    fn test_write_mp3_and_wav_metadata() {
+        let temp_dir = tempdir().expect("Failed to create temp dir");
+
+        for filename in ["sample.mp3", "sample.wav"] {
+            let mut source = song::Song {
+                directory: utils::get_tests_directory(),
+                filename: String::from(filename),
+                ..Default::default()
+            };
+            source.data = song::io::to_data(&source).unwrap();
+            let mut song = song::Song {
+                directory: String::from(temp_dir.path().to_str().unwrap()),
+                filename: String::from(filename),
+                ..Default::default()
+            };
+            if let Err(err) = song::io::copy_song(&source, &mut song) {
+                panic!("Error copying song: {err:?}");
+            }
+
+            if let Err(err) = song::io::read_metadata(&mut song) {
+                panic!("Error reading metadata: {err:?}");
+            }
+            song.title = String::from("Renamé");
+            song.album_artist = String::from("KD");
+            song.disc = 1;
+            song.disc_count = 2;
+
+            if let Err(err) = song::io::write_metadata(&song) {
+                panic!("Error writing metadata of {filename}: {err:?}");
+            }
+
+            match song::Song::from_file(&song.directory, &song.filename) {
+                Ok(written) => {
+                    assert_eq!(song.title, written.title);
+                    assert_eq!(song.artist, written.artist);
+                    assert_eq!(song.album_artist, written.album_artist);
+                    assert_eq!(song.year, written.year);
+                    assert_eq!(song.track, written.track);
+                    assert_eq!(1, written.disc);
+                    assert_eq!(2, written.disc_count);
+                }
+                Err(err) => panic!("Error reading metadata of {filename}: {err:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_calculate_duration() {
+        let expected = [
+            ("track01.flac", 1465303.0 / 48000.0),
+            ("sample.mp3", 77.0 * 1152.0 / 44100.0),
+            ("sample.wav", 2.0),
+        ];
+
+        for (filename, duration) in expected {
+            let mut song = song::Song {
+                directory: utils::get_tests_directory(),
+                filename: String::from(filename),
+                ..Default::default()
+            };
+
+            match song.calculate_duration() {
+                Ok(calculated) => assert!((calculated - duration).abs() < 0.001),
+                Err(err) => panic!("Error calculating duration of {filename}: {err:?}"),
+            }
+
+            song.data = song::io::to_data(&song).unwrap();
+            song.directory = String::new();
+            match song.calculate_duration() {
+                Ok(calculated) => assert!((calculated - duration).abs() < 0.001),
+                Err(err) => panic!("Error calculating duration of {filename}: {err:?}"),
+            }
+        }
+
+        match song::Song::from_file(&utils::get_tests_directory(), "sample.mp3") {
+            Ok(song) => assert_eq!(2, song.duration),
+            Err(err) => panic!("Error reading metadata: {err:?}"),
+        }
+    }
+
+    #[test]
+    fn test_track_from_song() {
+        let album_file = utils::get_tests_directory() + "album.json";
+        let album = album::collection::parse_album(&album_file).unwrap();
+
+        for (index, expected) in album.tracks.iter().enumerate() {
+            let filename = format!("track{:02}.flac", index + 1);
+            let song =
+                song::Song::from_flac_file(&utils::get_tests_directory(), &filename).unwrap();
+
+            match album::collection::Track::from_song(&song) {
+                Ok(track) => assert_eq!(expected.duration, track.duration.round()),
+                Err(err) => panic!("Error creating track: {err:?}"),
+            }
+        }
+    }
+
+    #[test]
+    fn test_calculate_audio_properties() {
+        let expected = [
+            ("track01.flac", "flac", 48000, 24, 2, true),
+            ("sample.mp3", "mp3", 44100, 0, 2, false),
+            ("sample.wav", "pcm", 8000, 16, 1, false),
+        ];
+
+        for (filename, codec, sample_rate, bit_depth, channels, hi_res) in expected {
+            let mut song = song::Song {
+                directory: utils::get_tests_directory(),
+                filename: String::from(filename),
+                ..Default::default()
+            };
+
+            match song.calculate_audio_properties() {
+                Ok(properties) => {
+                    assert_eq!(codec, properties.codec);
+                    assert_eq!(sample_rate, properties.sample_rate);
+                    assert_eq!(bit_depth, properties.bit_depth);
+                    assert_eq!(channels, properties.channels);
+                    assert_eq!(hi_res, properties.is_hi_res());
+                    assert!(properties.bitrate > 0);
+                    song.audio_properties = Some(properties);
+                }
+                Err(err) => panic!("Error getting audio properties of {filename}: {err:?}"),
+            }
+
+            let json = song.to_metadata_json(false).unwrap();
+            assert!(json.contains(&format!("\"sample_rate\":{sample_rate}")));
+        }
+
+        let song = song::Song {
+            directory: utils::get_tests_directory(),
+            filename: String::from("sample.mp3"),
+            ..Default::default()
+        };
+        assert_eq!(128, song.calculate_audio_properties().unwrap().bitrate);
+    }
+
+    fn original_header_length(data: &[u8]) -> usize {
+        let blocks = flac::read_blocks(&mut &data[..]).unwrap();
+        flac::header_length(&blocks)
+    }
+
+    #[test]
+    fn test_read_metadata_of_non_flac_file() {
+        let mut song = song::Song {
+            directory: utils::get_tests_directory(),
+            filename: String::from("album.json"),
+            ..Default::default()
+        };
+
+        assert!(song::io::read_metadata(&mut song).is_err());
+    }
+}
+
+#[cfg(test)]
+mod coverart_tests {
+    use tempfile::tempdir;
+
+    use crate::utils;
+    use icarus_models::coverart;
+    use icarus_models::song;
+    use icarus_models::types;
+
+    #[test]
+    fn test_embed_and_extract_coverart() {
+        let temp_dir = tempdir().expect("Failed to create temp dir");
+        let mut source = song::Song {
+            directory: utils::get_tests_directory(),
+            filename: String::from("track03.flac"),
+            ..Default::default()
+        };
+        source.data = song::io::to_data(&source).unwrap();
+        let mut song = song::Song {
+            id: uuid::Uuid::new_v4(),
+            directory: String::from(temp_dir.path().to_str().unwrap()),
+            filename: String::from("track03.flac"),
+            ..Default::default()
+        };
+        if let Err(err) = song::io::copy_song(&source, &mut song) {
+            panic!("Error copying song: {err:?}");
+        }
+
+        assert!(coverart::io::from_song(&song).is_err());
+
+        let mut coverart = coverart::init::init_coverart_dir_and_filename(
+            &utils::get_tests_directory(),
+            "coverart.png",
+        );
+        coverart.title = String::from("Sample Tracks! Yes");
+        coverart.file_type = String::from("png");
+        coverart.data = coverart::io::to_data(&coverart).unwrap();
+
+        if let Err(err) = coverart::io::embed_into_song(&coverart, &song) {
+            panic!("Error embedding cover art: {err:?}");
+        }
+
+        match coverart::io::from_song(&song) {
+            Ok(extracted) => {
+                assert_eq!(coverart.data, extracted.data);
+                assert_eq!(coverart.title, extracted.title);
+                assert_eq!("png", extracted.file_type);
+                assert_eq!((32, 32), (extracted.width, extracted.height));
+                assert_eq!(song.id, extracted.song_id);
+            }
+            Err(err) => panic!("Error extracting cover art: {err:?}"),
+        }
+
+        match song::Song::from_flac_file(&song.directory, &song.filename) {
+            Ok(song) => assert_eq!(22, song.duration),
+            Err(err) => panic!("Error reading metadata: {err:?}"),
+        }
+    }
+
+    #[test]
+    fn test_coverart_save_validated() {
+        let temp_dir = tempdir().expect("Failed to create temp dir");
+        let filepath = utils::get_tests_directory() + "coverart.png";
+        let mut coverart = coverart::init::init_coverart_dir_and_filename(
+            temp_dir.path().to_str().unwrap(),
+            "coverart-upload.jpg",
+        );
+        coverart.data = utils::extract_data_from_file(&filepath).unwrap();
+
+        assert_eq!(
+            types::CoverArtType::PngExtension,
+            coverart::detect_coverart_type(&coverart.data)
+        );
+        assert!(coverart.save_to_filesystem_validated().is_err());
+
+        coverart.filename = String::from("coverart-upload.png");
+        if let Err(err) = coverart.save_to_filesystem_validated() {
+            panic!("Error saving cover art: {err:?}");
+        }
+    }
+
+    #[test]
+    fn test_coverart_image_limits() {
+        let mut coverart = coverart::init::init_coverart_dir_and_filename(
+            &utils::get_tests_directory(),
+            "coverart.png",
+        );
+        if let Err(err) = coverart.inspect_image() {
+            panic!("Error inspecting cover art: {err:?}");
+        }
+
+        let size = std::fs::metadata(coverart.path().unwrap()).unwrap().len();
+        assert_eq!((32, 32), (coverart.width, coverart.height));
+        assert_eq!("image/png", coverart.mime_type);
+        assert_eq!(size as i64, coverart.size);
+
+        let mut limits = coverart::ImageLimits {
+            max_aspect_ratio: Some(1.0),
+            ..Default::default()
+        };
+        assert!(coverart.validate_limits(&limits).is_ok());
+
+        limits.max_width = 16;
+        assert!(matches!(
+            coverart.validate_limits(&limits),
+            Err(icarus_models::Error::ImageTooLarge)
+        ));
+
+        // Recorded dimensions are not trusted
+        coverart.width = 1;
+        coverart.height = 1;
+        assert!(matches!(
+            coverart.validate_limits(&limits),
+            Err(icarus_models::Error::ImageTooLarge)
+        ));
+
+        // The IHDR chunk of a PNG holds the width then the height
+        let mut data = coverart::io::to_data(&coverart).unwrap();
+        data[20..24].copy_from_slice(&16u32.to_be_bytes());
+        coverart.data = data.clone();
+        limits.max_width = coverart::DEFAULT_MAX_DIMENSION;
+        assert!(matches!(
+            coverart.validate_limits(&limits),
+            Err(icarus_models::Error::ImageNotSquare)
+        ));
+
+        data[16..20].copy_from_slice(&u32::MAX.to_be_bytes());
+        coverart.data = data;
+        limits.max_width = i32::MAX;
+        limits.max_aspect_ratio = None;
+        assert!(matches!(
+            coverart.validate_limits(&limits),
+            Err(icarus_models::Error::ImageTooLarge)
+        ));
+        assert!(matches!(
+            coverart.inspect_image(),
+            Err(icarus_models::Error::ImageTooLarge)
+        ));
+
+        coverart.data = vec![0, 1, 2, 3];
+        assert!(matches!(
+            coverart.inspect_image(),
+            Err(icarus_models::Error::InvalidImage)
+        ));
+    }
+
+    #[test]
+    fn test_create_thumbnails() {
+        let mut coverart = coverart::init::init_coverart_dir_and_filename(
+            &utils::get_tests_directory(),
+            "coverart.png",
+        );
+        coverart.id = uuid::Uuid::new_v4();
+        coverart.song_id = uuid::Uuid::new_v4();
+
+        let thumbnails =
+            match coverart::thumbnail::create_all(&coverart, &[16, 64], Default::default()) {
+                Ok(thumbnails) => thumbnails,
+                Err(err) => panic!("Error creating thumbnails: {err:?}"),
+            };
+
+        assert_eq!(2, thumbnails.len());
+        assert_eq!("coverart-16.jpeg", thumbnails[0].filename);
+        assert_eq!((16, 16), (thumbnails[0].width, thumbnails[0].height));
+        // Smaller images are not enlarged
+        assert_eq!((32, 32), (thumbnails[1].width, thumbnails[1].height));
+
+        for thumbnail in thumbnails.iter() {
+            assert_eq!(coverart.id, thumbnail.original_id);
+            assert_eq!(coverart.song_id, thumbnail.song_id);
+            assert_eq!("image/jpeg", thumbnail.mime_type);
+            assert_eq!(
+                types::CoverArtType::JpegExtension,
+                coverart::detect_coverart_type(&thumbnail.data)
+            );
+            assert!(thumbnail.validate_data().is_ok());
+        }
+
+        let wide = image::DynamicImage::new_rgba8(64, 32);
+        let mut data: Vec<u8> = Vec::new();
+        wide.write_to(
+            &mut std::io::Cursor::new(&mut data),
+            image::ImageFormat::Png,
+        )
+        .unwrap();
+        coverart.data = data;
+
+        let thumbnail =
+            coverart::thumbnail::create(&coverart, 16, coverart::thumbnail::Format::Png).unwrap();
+        assert_eq!((16, 8), (thumbnail.width, thumbnail.height));
+        assert_eq!("coverart-16.png", thumbnail.filename);
+
+        coverart.data = vec![0, 1, 2, 3];
+        assert!(matches!(
+            coverart::thumbnail::create(&coverart, 16, Default::default()),
+            Err(icarus_models::Error::InvalidImage)
+        ));
+    }
+
+    #[test]
+    fn test_webp_and_gif_coverart() {
+        let temp_dir = tempdir().expect("Failed to create temp dir");
+        let image = image::DynamicImage::new_rgba8(48, 24);
+
+        for (format, extension, coverart_type, mime_type) in [
+            (
+                image::ImageFormat::WebP,
+                "webp",
+                types::CoverArtType::WebpExtension,
+                "image/webp",
+            ),
+            (
+                image::ImageFormat::Gif,
+                "gif",
+                types::CoverArtType::GifExtension,
+                "image/gif",
+            ),
+        ] {
+            let mut data: Vec<u8> = Vec::new();
+            image
+                .write_to(&mut std::io::Cursor::new(&mut data), format)
+                .unwrap();
+
+            let filename = coverart::generate_filename(coverart_type, true).unwrap();
+            assert!(filename.ends_with(&format!(".{extension}")));
+            assert_eq!(
+                coverart_type,
+                types::CoverArtType::from_extension(extension)
+            );
+
+            let mut coverart = coverart::init::init_coverart_dir_and_filename(
+                temp_dir.path().to_str().unwrap(),
+                &filename,
+            );
+            coverart.data = data;
+            assert_eq!(
+                coverart_type,
+                coverart::detect_coverart_type(&coverart.data)
+            );
+            if let Err(err) = coverart.save_to_filesystem_validated() {
+                panic!("Error saving {extension} cover art: {err:?}");
+            }
+
+            coverart.inspect_image().unwrap();
+            assert_eq!((48, 24), (coverart.width, coverart.height));
+            assert_eq!(mime_type, coverart.mime_type);
+            assert_eq!(Some(mime_type), coverart::file_type_to_mime(extension));
+
+            let thumbnail = coverart::thumbnail::create(&coverart, 16, Default::default()).unwrap();
+            assert_eq!((16, 8), (thumbnail.width, thumbnail.height));
+        }
+    }
+
+    #[test]
+    fn test_coverart_checksum() {
+        let mut coverart = coverart::init::init_coverart_dir_and_filename(
+            &utils::get_tests_directory(),
+            "coverart.png",
+        );
+        assert!(coverart.verify_file().is_err());
+
+        if let Err(err) = coverart.update_checksum() {
+            panic!("Error computing checksum: {err:?}");
+        }
+        assert!(coverart.verify_file().unwrap());
+
+        coverart.data = coverart::io::to_data(&coverart).unwrap();
+        assert_eq!(coverart.checksum, coverart.compute_checksum().unwrap());
+
+        coverart.checksum = "0".repeat(64);
+        assert!(!coverart.verify_file().unwrap());
+    }
 }
 
 #[cfg(test)]
@@ -180,3 +1089,247 @@ mod album_tests {
         }
     }
 }
+
+#[cfg(test)]
+mod storage_tests {
+    use tempfile::tempdir;
+
+    use crate::utils;
+    use icarus_models::coverart;
+    use icarus_models::song;
+    use icarus_models::storage::layout;
+
+    #[test]
+    fn test_place_and_enumerate() {
+        let temp_dir = tempdir().expect("Failed to create temp dir");
+        let layout = layout::Layout::new(temp_dir.path(), layout::Key::Id);
+        let mut song = song::Song {
+            id: uuid::Uuid::parse_str("a1b2c3d4-0000-4000-8000-000000000001").unwrap(),
+            filename: String::from("upload.flac"),
+            data: vec![1, 2, 3],
+            ..Default::default()
+        };
+
+        if let Err(err) = layout.place_song(&mut song) {
+            panic!("Error placing song: {err:?}");
+        }
+        song.save_to_filesystem().unwrap();
+
+        let expected = temp_dir
+            .path()
+            .join("a1/b2/a1b2c3d4000040008000000000000001.flac");
+        assert_eq!(expected, song.path().unwrap());
+        assert_eq!(expected, layout.resolve_song(&song).unwrap());
+
+        let mut coverart = coverart::CoverArt {
+            id: uuid::Uuid::parse_str("ffee0000-0000-4000-8000-000000000002").unwrap(),
+            file_type: String::from("png"),
+            data: vec![4, 5, 6],
+            ..Default::default()
+        };
+        layout.place_coverart(&mut coverart).unwrap();
+        coverart.save_to_filesystem().unwrap();
+        assert!(coverart.filename.ends_with(".png"));
+
+        assert_eq!(
+            vec![expected, coverart.path().unwrap()],
+            layout.enumerate().unwrap()
+        );
+
+        song.id = uuid::Uuid::nil();
+        assert!(matches!(
+            layout.resolve_song(&song),
+            Err(icarus_models::Error::IdNotInitialized)
+        ));
+    }
+
+    #[test]
+    fn test_migrate_content_addressed() {
+        let temp_dir = tempdir().expect("Failed to create temp dir");
+        let flat_dir = temp_dir.path().join("flat");
+        std::fs::create_dir(&flat_dir).unwrap();
+        let layout = layout::Layout::new(temp_dir.path().join("store"), layout::Key::Checksum);
+
+        let source = song::Song {
+            directory: utils::get_tests_directory(),
+            filename: String::from("track01.flac"),
+            ..Default::default()
+        };
+        let mut songs: Vec<song::Song> = Vec::new();
+        for filename in ["track-a.flac", "track-b.flac"] {
+            let mut song = song::Song {
+                directory: String::from(flat_dir.to_str().unwrap()),
+                filename: String::from(filename),
+                ..Default::default()
+            };
+            song::io::copy_song_file(&source, &song).unwrap();
+            song.update_checksums().unwrap();
+            songs.push(song);
+        }
+
+        for song in songs.iter_mut() {
+            if let Err(err) = layout.migrate_song(song) {
+                panic!("Error migrating song: {err:?}");
+            }
+        }
+
+        assert_eq!(songs[0].path().unwrap(), songs[1].path().unwrap());
+        assert!(songs[0].verify_file().unwrap());
+        assert_eq!(0, std::fs::read_dir(&flat_dir).unwrap().count());
+        assert_eq!(1, layout.enumerate().unwrap().len());
+    }
+
+    #[test]
+    fn test_migrate_content_addressed_mismatch() {
+        let temp_dir = tempdir().expect("Failed to create temp dir");
+        let layout = layout::Layout::new(temp_dir.path().join("store"), layout::Key::Checksum);
+
+        let source = song::Song {
+            directory: utils::get_tests_directory(),
+            filename: String::from("track01.flac"),
+            ..Default::default()
+        };
+        let mut song = song::Song {
+            directory: String::from(temp_dir.path().to_str().unwrap()),
+            filename: String::from("track-a.flac"),
+            ..Default::default()
+        };
+        song::io::copy_song_file(&source, &song).unwrap();
+        song.update_checksums().unwrap();
+
+        let target = layout.resolve_song(&song).unwrap();
+        std::fs::create_dir_all(target.parent().unwrap()).unwrap();
+        std::fs::write(&target, b"different").unwrap();
+
+        assert!(matches!(
+            layout.migrate_song(&mut song),
+            Err(icarus_models::Error::FileAlreadyExists)
+        ));
+        assert!(song.verify_file().unwrap());
+    }
+
+    #[test]
+    fn test_memory_storage() {
+        use icarus_models::storage::Storage;
+
+        let storage = icarus_models::storage::MemoryStorage::new();
+        let mut song = song::Song {
+            directory: String::from("/music"),
+            filename: String::from("track01.flac"),
+            data: vec![1, 2, 3],
+            ..Default::default()
+        };
+
+        song.save_to_storage(&storage, icarus_models::types::WriteMode::CreateNew)
+            .unwrap();
+        assert!(
+            song.save_to_storage(&storage, icarus_models::types::WriteMode::CreateNew)
+                .is_err()
+        );
+        assert!(!song.path().unwrap().exists());
+        assert_eq!(
+            vec![1, 2, 3],
+            song::io::to_data_from_storage(&storage, &song).unwrap()
+        );
+
+        let coverart = coverart::CoverArt {
+            directory: String::from("/music"),
+            filename: String::from("coverart.png"),
+            data: vec![4, 5, 6],
+            ..Default::default()
+        };
+        coverart
+            .save_to_storage(&storage, icarus_models::types::WriteMode::Overwrite)
+            .unwrap();
+        assert_eq!(
+            vec![coverart.path().unwrap(), song.path().unwrap()],
+            storage.list(std::path::Path::new("/music")).unwrap()
+        );
+
+        song.remove_from_storage(&storage).unwrap();
+        coverart.remove_from_storage(&storage).unwrap();
+        assert!(storage.list(std::path::Path::new("/")).unwrap().is_empty());
+
+        song.data = Vec::new();
+        song.save_to_storage(&storage, icarus_models::types::WriteMode::Overwrite)
+            .unwrap();
+        assert!(matches!(
+            song::io::to_data_from_storage(&storage, &song),
+            Err(icarus_models::Error::FileEmpty)
+        ));
+    }
+
+    #[test]
+    fn test_song_io_in_memory_storage() {
+        use std::io::Read;
+
+        let storage = icarus_models::storage::MemoryStorage::new();
+        let source = song::Song {
+            directory: utils::get_tests_directory(),
+            filename: String::from("track03.flac"),
+            ..Default::default()
+        };
+        let mut song = song::Song {
+            id: uuid::Uuid::new_v4(),
+            directory: String::from("/music"),
+            filename: String::from("track03.flac"),
+            data: song::io::to_data(&source).unwrap(),
+            ..Default::default()
+        };
+        song.save_to_storage(&storage, icarus_models::types::WriteMode::CreateNew)
+            .unwrap();
+        song.data = Vec::new();
+        song.update_checksums_from_storage(&storage).unwrap();
+        assert!(!song.audio_md5.is_empty());
+        assert_eq!(
+            22,
+            song.calculate_duration_from_storage(&storage)
+                .unwrap()
+                .round() as i32
+        );
+        assert!(song.verify_file_in_storage(&storage).unwrap());
+        assert!(song.verify_file().is_err());
+
+        let mut copy = song::Song {
+            directory: String::from("/music"),
+            filename: String::from("track03-copy.flac"),
+            ..Default::default()
+        };
+        song::io::copy_song_file_in_storage(&storage, &song, &copy).unwrap();
+        assert_eq!(
+            song.checksum,
+            copy.compute_checksum_from_storage(&storage).unwrap()
+        );
+
+        let size = song::io::file_size_from_storage(&storage, &copy).unwrap();
+        let mut range: Vec<u8> = Vec::new();
+        song::io::open_range_from_storage(&storage, &copy, size - 10, None)
+            .unwrap()
+            .read_to_end(&mut range)
+            .unwrap();
+        assert_eq!(10, range.len());
+
+        copy.title = String::from("Stored");
+        copy.year = 2024;
+        song::io::write_metadata_to_storage(&storage, &copy).unwrap();
+        let mut written = copy.clone();
+        written.title = String::new();
+        written.year = 0;
+        song::io::read_metadata_from_storage(&storage, &mut written).unwrap();
+        assert_eq!(("Stored", 2024), (written.title.as_str(), written.year));
+
+        let mut coverart = coverart::init::init_coverart_dir_and_filename(
+            &utils::get_tests_directory(),
+            "coverart.png",
+        );
+        coverart.file_type = String::from("png");
+        coverart.data = coverart::io::to_data(&coverart).unwrap();
+        coverart::io::embed_into_song_in_storage(&storage, &coverart, &song).unwrap();
+        assert!(!song.verify_file_in_storage(&storage).unwrap());
+        match coverart::io::from_song_in_storage(&storage, &song) {
+            Ok(extracted) => assert_eq!(coverart.data, extracted.data),
+            Err(err) => panic!("Error extracting cover art: {err:?}"),
+        }
+        assert!(!song.path().unwrap().exists());
+    }
+}